@@ -0,0 +1,372 @@
+//! `replay-events` command, publishes archived SES events again, eg: events exported from S3/Firehose
+//! that consumers missed while they were down
+
+use crate::{
+    config::AppConfig,
+    controller::dto::events::EmailEvent,
+    queue::server::Server,
+    ses::{
+        dedup::SnsMessageDeduplicator,
+        handler::{self, SesEventHandler},
+    },
+    storage::{repository::Repository, sqlite::SqliteRepository},
+};
+use serde_json::Value;
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+pub static USAGE: &str = "usage: mailer replay-events [--dry-run] [--uuid <request uuid>]... [--event-type <event type>]... <file or directory>...
+
+reads SNS notifications or raw SES events, as JSON objects one per line (JSON Lines),
+from the given files or every file in the given directories and publishes them to the events exchange
+
+  --dry-run      only print the events that would be published
+  --uuid         only replay events of this request, can be repeated
+  --event-type   only replay events of this type (eg: delivery, bounce, open), can be repeated";
+
+#[derive(Debug, Default)]
+pub struct ReplayOptions {
+    pub dry_run: bool,
+    pub request_uuids: Vec<String>,
+    pub event_types: Vec<String>,
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Default)]
+struct ReplayReport {
+    read: usize,
+    invalid: usize,
+    filtered: usize,
+    published: usize,
+    failed: usize,
+}
+
+impl ReplayOptions {
+    pub fn from_args(args: &[String]) -> Result<ReplayOptions, String> {
+        let mut options = ReplayOptions::default();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--dry-run" => options.dry_run = true,
+                "--uuid" => options
+                    .request_uuids
+                    .push(args.next().ok_or("--uuid requires a value")?.to_owned()),
+                "--event-type" => options.event_types.push(
+                    args.next()
+                        .ok_or("--event-type requires a value")?
+                        .to_owned(),
+                ),
+                flag if flag.starts_with("--") => return Err(format!("unknown option: {}", flag)),
+                path => options.paths.push(PathBuf::from(path)),
+            }
+        }
+
+        if options.paths.is_empty() {
+            return Err("at least one file or directory is required".to_owned());
+        }
+
+        Ok(options)
+    }
+
+    fn matches(&self, event: &EmailEvent) -> bool {
+        (self.request_uuids.is_empty() || self.request_uuids.contains(&event.request_uuid))
+            && (self.event_types.is_empty() || self.event_types.contains(&event.event_type))
+    }
+}
+
+/// lists the files to read, files inside directories are read in name order so replays are deterministic
+fn collect_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut files = vec![];
+
+    for path in paths {
+        if path.is_dir() {
+            let mut dir_files = fs::read_dir(path)
+                .map_err(|e| format!("failed to read directory {}: {}", path.display(), e))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.is_file())
+                .collect::<Vec<_>>();
+
+            dir_files.sort();
+            files.append(&mut dir_files);
+        } else {
+            files.push(path.to_owned());
+        }
+    }
+
+    Ok(files)
+}
+
+/// SNS notifications carry the SES event as a JSON string in their `Message`, anything else is handled as a raw SES event
+fn get_email_event(value: Value) -> Result<EmailEvent, String> {
+    match value.get("Message").and_then(|m| m.as_str()) {
+        Some(message) if value.get("Type").is_some() => {
+            handler::get_email_event_from_ses_message(message)
+        }
+        _ => handler::get_email_event_from_ses_message(&value.to_string()),
+    }
+}
+
+async fn replay_file(
+    file: &Path,
+    options: &ReplayOptions,
+    ses_event_handler: Option<&SesEventHandler>,
+    report: &mut ReplayReport,
+) -> Result<(), String> {
+    let reader = fs::File::open(file)
+        .map(BufReader::new)
+        .map_err(|e| format!("failed to open file {}: {}", file.display(), e))?;
+
+    // read line by line, so large exports are not loaded in memory at once
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| format!("failed to read file {}: {}", file.display(), e))?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        report.read += 1;
+
+        let value = match serde_json::from_str::<Value>(&line) {
+            Ok(value) => value,
+            Err(e) => {
+                report.invalid += 1;
+                println!(
+                    "[REPLAY] skipping invalid JSON in {} line {}: {}",
+                    file.display(),
+                    index + 1,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let email_event = match get_email_event(value) {
+            Ok(email_event) => email_event,
+            Err(e) => {
+                report.invalid += 1;
+                println!(
+                    "[REPLAY] skipping invalid event in {}: {}",
+                    file.display(),
+                    e
+                );
+                continue;
+            }
+        };
+
+        if !options.matches(&email_event) {
+            report.filtered += 1;
+            continue;
+        }
+
+        let Some(ses_event_handler) = ses_event_handler else {
+            report.published += 1;
+            println!(
+                "[REPLAY] would publish {} event of request {} (ses message id: {})",
+                email_event.event_type, email_event.request_uuid, email_event.mail.message_id
+            );
+            continue;
+        };
+
+        match ses_event_handler.handle(email_event, None).await {
+            Ok(_) => report.published += 1,
+            Err(e) => {
+                report.failed += 1;
+                println!("[REPLAY] failed to publish event: {}", e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// replays the events of the given files, returning a error if any event could not be published
+pub async fn run(cfg: &AppConfig, options: ReplayOptions) -> Result<(), String> {
+    let files = collect_files(&options.paths)?;
+
+    let ses_event_handler = if options.dry_run {
+        None
+    } else {
+        // deliveries are never consumed since the server only publishes
+        let (sender, _) = mpsc::unbounded_channel();
+        let server = Arc::new(Server::new(cfg, sender));
+
+        server
+            .connect()
+            .await
+            .map_err(|e| format!("failed to connect to RMQ: {}", e))?;
+
+        let repository: Arc<dyn Repository> = Arc::new(SqliteRepository::new(
+            &cfg.db_sqlite_path,
+            cfg.db_pool_size,
+        )?);
+
+        // replayed events are published even if already handled, so deduplication and resends are disabled
+        let deduplicator = SnsMessageDeduplicator::new(Duration::ZERO, None);
+
+        Some((
+            server.clone(),
+            SesEventHandler::new(
+                server,
+                repository,
+                deduplicator,
+                cfg.rmq_email_events_format,
+                cfg.rmq_email_events_per_recipient,
+                None,
+            ),
+        ))
+    };
+
+    let mut report = ReplayReport::default();
+
+    for file in files {
+        replay_file(
+            &file,
+            &options,
+            ses_event_handler.as_ref().map(|(_, handler)| handler),
+            &mut report,
+        )
+        .await?;
+    }
+
+    if let Some((server, _)) = ses_event_handler {
+        server.shutdown().await;
+    }
+
+    println!(
+        "[REPLAY] read: {}, invalid: {}, filtered out: {}, {}: {}, failed: {}",
+        report.read,
+        report.invalid,
+        report.filtered,
+        if options.dry_run {
+            "would publish"
+        } else {
+            "published"
+        },
+        report.published,
+        report.failed
+    );
+
+    if report.failed > 0 {
+        return Err(format!("{} events could not be published", report.failed));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SES_EVENT: &str = r#"{
+        "eventType": "Delivery",
+        "mail": {
+            "timestamp": "2026-10-01T12:00:00.000Z",
+            "messageId": "0100018a-message-id",
+            "sourceArn": null,
+            "sendingAccountId": "123456789012",
+            "destination": ["a@example.com"],
+            "headersTruncated": false,
+            "headers": [],
+            "commonHeaders": {},
+            "tags": {"request_uuid": ["6f0c4a8e-1b7e-4a55-9a51-7b0b6c1f3a10"]}
+        },
+        "delivery": {
+            "timestamp": "2026-10-01T12:00:01.000Z",
+            "processingTimeMillis": 1000,
+            "recipients": ["a@example.com"],
+            "smtpResponse": "250 ok",
+            "reportingMTA": "a8-1.smtp-out.amazonses.com"
+        }
+    }"#;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_options_from_args() {
+        let options = ReplayOptions::from_args(&args(&[
+            "--dry-run",
+            "--uuid",
+            "a",
+            "--event-type",
+            "bounce",
+            "events.jsonl",
+            "--uuid",
+            "b",
+            "archive/",
+        ]))
+        .unwrap();
+
+        assert!(options.dry_run);
+        assert_eq!(options.request_uuids, vec!["a", "b"]);
+        assert_eq!(options.event_types, vec!["bounce"]);
+        assert_eq!(
+            options.paths,
+            vec![PathBuf::from("events.jsonl"), PathBuf::from("archive/")]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_args() {
+        for invalid_args in [
+            args(&[]),
+            args(&["--dry-run"]),
+            args(&["events.jsonl", "--uuid"]),
+            args(&["events.jsonl", "--event-type"]),
+            args(&["events.jsonl", "--verbose"]),
+        ] {
+            assert!(
+                ReplayOptions::from_args(&invalid_args).is_err(),
+                "{:?}",
+                invalid_args
+            );
+        }
+    }
+
+    #[test]
+    fn gets_events_from_raw_ses_events() {
+        let value = serde_json::from_str::<Value>(SES_EVENT).unwrap();
+
+        let email_event = get_email_event(value).unwrap();
+
+        assert_eq!(
+            email_event.request_uuid,
+            "6f0c4a8e-1b7e-4a55-9a51-7b0b6c1f3a10"
+        );
+        assert_eq!(email_event.event_type, "delivery");
+    }
+
+    #[test]
+    fn gets_events_from_sns_notifications() {
+        let value = serde_json::json!({
+            "Type": "Notification",
+            "MessageId": "22b80b92-fdea-4c2c-8f9d-bdfb0c7bf324",
+            "TopicArn": "arn:aws:sns:us-east-1:123456789012:ses-events",
+            "Message": SES_EVENT,
+        });
+
+        let email_event = get_email_event(value).unwrap();
+
+        assert_eq!(
+            email_event.request_uuid,
+            "6f0c4a8e-1b7e-4a55-9a51-7b0b6c1f3a10"
+        );
+        assert_eq!(email_event.event_type, "delivery");
+    }
+
+    #[test]
+    fn rejects_values_that_are_not_events() {
+        let value = serde_json::json!({ "Type": "Notification", "Message": "not a SES event" });
+
+        assert!(get_email_event(value).is_err());
+        assert!(get_email_event(serde_json::json!([1, 2, 3])).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! `ses-suppressions` command, synchronizes the local suppression list with the SES account suppression list
+
+use crate::{
+    config::AppConfig,
+    controller::dto::suppression::SesSuppressionSyncMode,
+    ses::{client::new_ses_client, suppression_sync::SesSuppressionSync},
+    storage::{repository::Repository, sqlite::SqliteRepository},
+};
+use std::sync::Arc;
+
+pub static USAGE: &str = "usage: mailer ses-suppressions <import|export|reconcile> [--prune]
+
+  import      adds the addresses of the SES account suppression list to the local list
+  export      adds the addresses of the local suppression list to the SES account suppression list
+  reconcile   imports and exports, so both lists contain the same addresses
+  --prune     on import or export, deletes the addresses missing on the source list from the target list";
+
+#[derive(Debug)]
+pub struct SuppressionsOptions {
+    pub mode: SesSuppressionSyncMode,
+    pub prune: bool,
+}
+
+impl SuppressionsOptions {
+    pub fn from_args(args: &[String]) -> Result<SuppressionsOptions, String> {
+        let mut mode = None;
+        let mut prune = false;
+
+        for arg in args {
+            match arg.as_str() {
+                "--prune" => prune = true,
+                flag if flag.starts_with("--") => return Err(format!("unknown option: {}", flag)),
+                value if mode.is_none() => {
+                    mode = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("unknown sync mode: {}", value))?,
+                    )
+                }
+                value => return Err(format!("unexpected argument: {}", value)),
+            }
+        }
+
+        Ok(SuppressionsOptions {
+            mode: mode.ok_or("a sync mode is required")?,
+            prune,
+        })
+    }
+}
+
+pub async fn run(cfg: &AppConfig, options: SuppressionsOptions) -> Result<(), String> {
+    let repository: Arc<dyn Repository> = Arc::new(SqliteRepository::new(
+        &cfg.db_sqlite_path,
+        cfg.db_pool_size,
+    )?);
+
+    SesSuppressionSync::new(new_ses_client(cfg).await, repository)
+        .run(options.mode, options.prune)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_options_from_args() {
+        let options = SuppressionsOptions::from_args(&args(&["export", "--prune"])).unwrap();
+        assert_eq!(options.mode, SesSuppressionSyncMode::Export);
+        assert!(options.prune);
+
+        let options = SuppressionsOptions::from_args(&args(&["reconcile"])).unwrap();
+        assert_eq!(options.mode, SesSuppressionSyncMode::Reconcile);
+        assert!(!options.prune);
+    }
+
+    #[test]
+    fn rejects_invalid_args() {
+        for invalid_args in [
+            args(&[]),
+            args(&["--prune"]),
+            args(&["sync"]),
+            args(&["import", "export"]),
+            args(&["import", "--force"]),
+        ] {
+            assert!(
+                SuppressionsOptions::from_args(&invalid_args).is_err(),
+                "{:?}",
+                invalid_args
+            );
+        }
+    }
+}
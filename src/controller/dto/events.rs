@@ -1,19 +1,22 @@
 //! DTOS for all the events that are fired by this service
 
-use super::{input::SendEmailIn, ses};
+use super::{input::SendEmailIn, output::SenderWarmupProgress, ses};
 use crate::queue::server::Routable;
 use chrono::{DateTime, Utc};
+use ring::digest;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(strum_macros::Display, Deserialize, Serialize)]
 pub enum EmailRequestStatus {
     STARTED,
     REJECTED,
+    SCHEDULED,
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub enum Email {
     open(ses::OpenObj),
     send(ses::SendObj),
@@ -37,6 +40,10 @@ pub struct EmailSendingReceivedEvent {
     pub request_uuid: Uuid,
 
     pub request: SendEmailIn,
+
+    /// why the request was rejected, only set for rejected requests
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub reason: Option<String>,
 }
 
 impl EmailSendingReceivedEvent {
@@ -46,15 +53,31 @@ impl EmailSendingReceivedEvent {
             request_uuid,
             timestamp: Utc::now(),
             status: EmailRequestStatus::STARTED,
+            reason: None,
         }
     }
 
-    pub fn rejected(request_uuid: uuid::Uuid, request: SendEmailIn) -> EmailSendingReceivedEvent {
+    pub fn rejected(
+        request_uuid: uuid::Uuid,
+        request: SendEmailIn,
+        reason: String,
+    ) -> EmailSendingReceivedEvent {
         EmailSendingReceivedEvent {
             request,
             request_uuid,
             timestamp: Utc::now(),
             status: EmailRequestStatus::REJECTED,
+            reason: Some(reason),
+        }
+    }
+
+    pub fn scheduled(request_uuid: uuid::Uuid, request: SendEmailIn) -> EmailSendingReceivedEvent {
+        EmailSendingReceivedEvent {
+            request,
+            request_uuid,
+            timestamp: Utc::now(),
+            status: EmailRequestStatus::SCHEDULED,
+            reason: None,
         }
     }
 }
@@ -64,6 +87,7 @@ impl Routable for EmailSendingReceivedEvent {
         match self.status {
             EmailRequestStatus::STARTED => format!("sending.{}.started", self.request_uuid),
             EmailRequestStatus::REJECTED => format!("sending.{}.rejected", self.request_uuid),
+            EmailRequestStatus::SCHEDULED => format!("sending.{}.scheduled", self.request_uuid),
         }
     }
 }
@@ -92,6 +116,59 @@ impl EmailRequestFinishedEvent {
     }
 }
 
+/// informs that a request reached its `expiresAt` before being sent, so none of its emails were sent
+#[derive(Deserialize, Serialize)]
+pub struct EmailRequestExpiredEvent {
+    pub timestamp: DateTime<Utc>,
+
+    pub request_uuid: Uuid,
+
+    pub expires_at: DateTime<Utc>,
+}
+
+impl EmailRequestExpiredEvent {
+    pub fn new(request_uuid: Uuid, expires_at: DateTime<Utc>) -> EmailRequestExpiredEvent {
+        EmailRequestExpiredEvent {
+            request_uuid,
+            expires_at,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+impl Routable for EmailRequestExpiredEvent {
+    fn routing_key(&self) -> String {
+        format!("sending.{}.expired", self.request_uuid)
+    }
+}
+
+/// informs that a request was cancelled before all its emails were sent
+#[derive(Deserialize, Serialize)]
+pub struct EmailRequestCancelledEvent {
+    pub timestamp: DateTime<Utc>,
+
+    pub request_uuid: Uuid,
+
+    /// recipients whose email was sent before the cancellation, empty for scheduled requests
+    pub sent_recipients: Vec<String>,
+}
+
+impl EmailRequestCancelledEvent {
+    pub fn new(request_uuid: Uuid, sent_recipients: Vec<String>) -> EmailRequestCancelledEvent {
+        EmailRequestCancelledEvent {
+            request_uuid,
+            sent_recipients,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+impl Routable for EmailRequestCancelledEvent {
+    fn routing_key(&self) -> String {
+        format!("sending.{}.cancelled", self.request_uuid)
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct EmailSendingErrorEvent {
     pub timestamp: DateTime<Utc>,
@@ -124,7 +201,233 @@ impl Routable for EmailSendingErrorEvent {
     }
 }
 
+/// informs that recipients of a request were not sent the email because they are in the suppression list
+#[derive(Deserialize, Serialize)]
+pub struct EmailSuppressedEvent {
+    pub timestamp: DateTime<Utc>,
+
+    pub request_uuid: Uuid,
+
+    pub recipients: Vec<String>,
+}
+
+impl EmailSuppressedEvent {
+    pub fn new(request_uuid: Uuid, recipients: Vec<String>) -> EmailSuppressedEvent {
+        EmailSuppressedEvent {
+            request_uuid,
+            recipients,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+impl Routable for EmailSuppressedEvent {
+    fn routing_key(&self) -> String {
+        format!("sending.{}.suppressed", self.request_uuid)
+    }
+}
+
+/// informs that a recipient kept bouncing transiently after every resend allowed by the resend policy
+#[derive(Deserialize, Serialize)]
+pub struct EmailResendGaveUpEvent {
+    pub timestamp: DateTime<Utc>,
+
+    pub request_uuid: Uuid,
+
+    pub recipient: String,
+
+    /// amount of resends done before giving up
+    pub attempts: u32,
+
+    /// bounce type and diagnostic of the last bounce
+    pub last_bounce: String,
+}
+
+impl EmailResendGaveUpEvent {
+    pub fn new(
+        request_uuid: Uuid,
+        recipient: String,
+        attempts: u32,
+        last_bounce: String,
+    ) -> EmailResendGaveUpEvent {
+        EmailResendGaveUpEvent {
+            request_uuid,
+            recipient,
+            attempts,
+            last_bounce,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+impl Routable for EmailResendGaveUpEvent {
+    fn routing_key(&self) -> String {
+        format!("sending.{}.gave_up", self.request_uuid)
+    }
+}
+
+/// informs that a request was rejected since its emails would exceed the daily quota of its tenant
 #[derive(Deserialize, Serialize)]
+pub struct EmailRequestOverQuotaEvent {
+    pub timestamp: DateTime<Utc>,
+
+    pub request_uuid: Uuid,
+
+    pub tenant: String,
+
+    /// maximum amount of emails of the tenant per 24 hours
+    pub daily_quota: u64,
+
+    /// emails of the tenant in the last 24 hours
+    pub used: u64,
+
+    /// emails of the rejected request
+    pub requested: u64,
+}
+
+impl EmailRequestOverQuotaEvent {
+    pub fn new(
+        request_uuid: Uuid,
+        tenant: String,
+        daily_quota: u64,
+        used: u64,
+        requested: u64,
+    ) -> EmailRequestOverQuotaEvent {
+        EmailRequestOverQuotaEvent {
+            request_uuid,
+            tenant,
+            daily_quota,
+            used,
+            requested,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+impl Routable for EmailRequestOverQuotaEvent {
+    fn routing_key(&self) -> String {
+        format!("sending.{}.over_quota", self.request_uuid)
+    }
+}
+
+/// informs that the SES account is close to its 24 hour sending quota
+#[derive(Deserialize, Serialize)]
+pub struct SesQuotaWarningEvent {
+    pub timestamp: DateTime<Utc>,
+
+    /// maximum amount of emails the account can send in 24 hours
+    pub max_24_hour_send: f64,
+
+    /// amount of emails sent by the account in the last 24 hours
+    pub sent_last_24_hours: f64,
+
+    /// maximum amount of emails the account can send per second
+    pub max_send_rate: f64,
+
+    /// if sendEmail deliveries were paused until the quota frees up
+    pub sending_paused: bool,
+}
+
+impl SesQuotaWarningEvent {
+    pub fn new(
+        max_24_hour_send: f64,
+        sent_last_24_hours: f64,
+        max_send_rate: f64,
+        sending_paused: bool,
+    ) -> SesQuotaWarningEvent {
+        SesQuotaWarningEvent {
+            max_24_hour_send,
+            sent_last_24_hours,
+            max_send_rate,
+            sending_paused,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+impl Routable for SesQuotaWarningEvent {
+    fn routing_key(&self) -> String {
+        "quota.ses.warning".to_owned()
+    }
+}
+
+/// informs that a request was deferred because its emails exceed the warm-up cap of its sender identity for today,
+/// the request is stored as scheduled and sent at `send_at`
+#[derive(Deserialize, Serialize)]
+pub struct EmailRequestDeferredEvent {
+    pub timestamp: DateTime<Utc>,
+
+    pub request_uuid: Uuid,
+
+    /// sender address or domain whose warm-up cap was reached
+    pub identity: String,
+
+    /// emails the identity can send today
+    pub daily_cap: u64,
+
+    /// emails reserved by the identity today
+    pub used: u64,
+
+    /// emails of the deferred request
+    pub requested: u64,
+
+    pub send_at: DateTime<Utc>,
+}
+
+impl EmailRequestDeferredEvent {
+    pub fn new(
+        request_uuid: Uuid,
+        identity: String,
+        daily_cap: u64,
+        used: u64,
+        requested: u64,
+        send_at: DateTime<Utc>,
+    ) -> EmailRequestDeferredEvent {
+        EmailRequestDeferredEvent {
+            request_uuid,
+            identity,
+            daily_cap,
+            used,
+            requested,
+            send_at,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+impl Routable for EmailRequestDeferredEvent {
+    fn routing_key(&self) -> String {
+        format!("sending.{}.deferred", self.request_uuid)
+    }
+}
+
+/// informs the warm-up progress of a sender identity, published when it sends its first emails of a day
+/// and when it reaches the cap of a day
+#[derive(Deserialize, Serialize)]
+pub struct SenderWarmupProgressEvent {
+    pub timestamp: DateTime<Utc>,
+
+    #[serde(flatten)]
+    pub progress: SenderWarmupProgress,
+}
+
+impl SenderWarmupProgressEvent {
+    pub fn new(progress: SenderWarmupProgress) -> SenderWarmupProgressEvent {
+        SenderWarmupProgressEvent {
+            progress,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+impl Routable for SenderWarmupProgressEvent {
+    fn routing_key(&self) -> String {
+        // identities contain dots, which separate the words of topic routing keys
+        "warmup.sender.progress".to_owned()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
 pub struct EmailEvent {
     /// uuid of the mail request that generated this event, extracted from the `mail` field
     pub request_uuid: String,
@@ -139,10 +442,470 @@ pub struct EmailEvent {
 
     /// raw SES event
     pub event: Email,
+
+    /// set when the event was split by recipient, in which case the recipient lists of `event` only contain this recipient
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub recipient: Option<String>,
+}
+
+impl EmailEvent {
+    /// email addresses affected by the event, events without a explicit list
+    /// of recipients (eg: opens and clicks) affect every mail destination
+    pub fn recipients(&self) -> Vec<String> {
+        match &self.event {
+            Email::bounce(bounce) => bounce
+                .bounced_recipients
+                .iter()
+                .map(|r| r.email_address.clone())
+                .collect(),
+            Email::complaint(complaint) => complaint
+                .complained_recipients
+                .iter()
+                .map(|r| r.email_address.clone())
+                .collect(),
+            Email::delivery(delivery) => delivery.recipients.clone(),
+            Email::delivery_delay(delay) => delay
+                .delayed_recipients
+                .iter()
+                .map(|r| r.email_address.clone())
+                .collect(),
+            _ => self.mail.destination.clone(),
+        }
+    }
+
+    /// if the event lists the recipients it affected, which SES does for deliveries, bounces, complaints and
+    /// delivery delays, other events (eg: opens and clicks) do not tell which recipient of the email caused them,
+    /// events with empty lists are handled as events without them, so they are still published
+    fn has_recipient_lists(&self) -> bool {
+        matches!(
+            &self.event,
+            Email::bounce(_) | Email::complaint(_) | Email::delivery(_) | Email::delivery_delay(_)
+        ) && !self.recipients().is_empty()
+    }
+
+    /// splits the event in one event per affected recipient, keeping only the recipient in the event recipient lists,
+    /// events without recipient lists are not split, since each copy would claim that every recipient caused it
+    pub fn split_by_recipient(&self) -> Vec<EmailEvent> {
+        if !self.has_recipient_lists() {
+            return vec![self.clone()];
+        }
+
+        self.recipients()
+            .into_iter()
+            .map(|recipient| {
+                let mut event = self.event.clone();
+
+                match &mut event {
+                    Email::bounce(bounce) => bounce
+                        .bounced_recipients
+                        .retain(|r| r.email_address == recipient),
+                    Email::complaint(complaint) => complaint
+                        .complained_recipients
+                        .retain(|r| r.email_address == recipient),
+                    Email::delivery(delivery) => delivery.recipients.retain(|r| *r == recipient),
+                    Email::delivery_delay(delay) => delay
+                        .delayed_recipients
+                        .retain(|r| r.email_address == recipient),
+                    _ => {}
+                }
+
+                EmailEvent {
+                    event,
+                    request_uuid: self.request_uuid.clone(),
+                    event_type: self.event_type.clone(),
+                    mail: self.mail.clone(),
+                    recipient: Some(recipient),
+                }
+            })
+            .collect()
+    }
+
+    /// converts the raw event to one provider neutral event for each affected recipient, events without recipient
+    /// lists are converted to a single event whose recipient is only set if the email had a single destination
+    pub fn normalize(&self) -> Vec<NormalizedEmailEvent> {
+        let (kind, timestamp) = match &self.event {
+            Email::send(_) => (EmailEventKind::Sent, self.mail.timestamp),
+            Email::open(open) => (EmailEventKind::Opened, open.timestamp),
+            Email::click(click) => (EmailEventKind::Clicked, click.timestamp),
+            Email::bounce(bounce) => (EmailEventKind::Bounced, bounce.timestamp),
+            Email::reject(_) => (EmailEventKind::Rejected, self.mail.timestamp),
+            Email::failure(_) => (EmailEventKind::Failed, self.mail.timestamp),
+            Email::delivery(delivery) => (EmailEventKind::Delivered, delivery.timestamp),
+            Email::complaint(complaint) => (EmailEventKind::Complained, complaint.timestamp),
+            Email::subscription(sub) => (EmailEventKind::SubscriptionChanged, sub.timestamp),
+            Email::delivery_delay(_) => (EmailEventKind::DeliveryDelayed, self.mail.timestamp),
+        };
+
+        let (user_agent, ip_address, link) = match &self.event {
+            Email::open(open) => (Some(&open.user_agent), Some(&open.ip_address), None),
+            Email::click(click) => (
+                Some(&click.user_agent),
+                Some(&click.ip_address),
+                Some(&click.link),
+            ),
+            Email::complaint(complaint) => (Some(&complaint.user_agent), None, None),
+            _ => (None, None, None),
+        };
+
+        let reason = match &self.event {
+            Email::reject(reject) => Some(reject.reason.clone()),
+            Email::failure(failure) => Some(failure.error_message.clone()),
+            Email::complaint(complaint) => Some(complaint.complaint_feedback_type.clone()),
+            Email::delivery_delay(delay) => Some(delay.delay_type.clone()),
+            _ => None,
+        };
+
+        // opens and clicks of a email sent to many recipients cannot be attributed to any of them
+        let recipients = if self.has_recipient_lists() {
+            self.recipients().into_iter().map(Some).collect()
+        } else {
+            match self.mail.destination.as_slice() {
+                [recipient] => vec![Some(recipient.clone())],
+                _ => vec![None],
+            }
+        };
+
+        recipients
+            .into_iter()
+            .map(|recipient| {
+                let (bounce, diagnostic_code) = match &self.event {
+                    Email::bounce(bounce) => (
+                        Some(BounceClassification {
+                            bounce_type: bounce
+                                .bounce_type
+                                .parse()
+                                .unwrap_or(BounceType::Undetermined),
+                            bounce_sub_type: bounce.bounce_sub_type.clone(),
+                        }),
+                        bounce
+                            .bounced_recipients
+                            .iter()
+                            .find(|r| Some(&r.email_address) == recipient.as_ref())
+                            .map(|r| r.diagnostic_code.clone()),
+                    ),
+                    Email::delivery_delay(delay) => (
+                        None,
+                        delay
+                            .delayed_recipients
+                            .iter()
+                            .find(|r| Some(&r.email_address) == recipient.as_ref())
+                            .map(|r| r.diagnostic_code.clone()),
+                    ),
+                    _ => (None, None),
+                };
+
+                NormalizedEmailEvent {
+                    request_uuid: self.request_uuid.clone(),
+                    recipient,
+                    kind,
+                    timestamp,
+                    provider: EMAIL_PROVIDER.to_owned(),
+                    provider_message_id: self.mail.message_id.clone(),
+                    bounce,
+                    diagnostic_code,
+                    reason: reason.clone(),
+                    link: link.cloned(),
+                    user_agent: user_agent.cloned(),
+                    ip_address: ip_address.cloned(),
+                }
+            })
+            .collect()
+    }
 }
 
 impl Routable for EmailEvent {
     fn routing_key(&self) -> String {
-        format!("email.{}.{}", self.request_uuid, self.event_type)
+        match &self.recipient {
+            Some(recipient) => format!(
+                "email.{}.{}.{}",
+                self.request_uuid,
+                self.event_type,
+                recipient_hash(recipient)
+            ),
+            None => format!("email.{}.{}", self.request_uuid, self.event_type),
+        }
+    }
+}
+
+/// hex encoded SHA-256 of the trimmed and lowercased email address, used in routing keys
+/// so consumers can bind to the events of a address without exposing it in the routing key
+pub fn recipient_hash(email: &str) -> String {
+    let digest = digest::digest(&digest::SHA256, email.trim().to_lowercase().as_bytes());
+
+    digest
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// name of the provider that sent the emails, present on normalized events
+static EMAIL_PROVIDER: &str = "ses";
+
+#[derive(Debug, Clone, Copy, strum_macros::Display, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum EmailEventKind {
+    Sent,
+    Delivered,
+    DeliveryDelayed,
+    Bounced,
+    Complained,
+    Rejected,
+    Failed,
+    Opened,
+    Clicked,
+    SubscriptionChanged,
+}
+
+#[derive(Debug, strum_macros::EnumString, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BounceType {
+    /// the address will never accept emails (eg: it does not exist), it should not receive emails again
+    Permanent,
+    /// the address could not receive the email at the time (eg: mailbox full)
+    Transient,
+    Undetermined,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BounceClassification {
+    pub bounce_type: BounceType,
+
+    /// provider specific detail of the bounce type, eg: `General`, `NoEmail`, `MailboxFull`
+    pub bounce_sub_type: String,
+}
+
+/// provider neutral event that affected a single recipient of a email request,
+/// consumers should prefer these over the raw `EmailEvent` as they do not depend on SES formats
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NormalizedEmailEvent {
+    pub request_uuid: String,
+
+    /// None if the event cannot be attributed to a recipient, eg: a open of a email sent to many recipients
+    pub recipient: Option<String>,
+
+    pub kind: EmailEventKind,
+
+    /// when the event happened, according to the provider
+    pub timestamp: DateTime<Utc>,
+
+    pub provider: String,
+
+    /// id of the email in the provider, shared by every recipient of the same email
+    pub provider_message_id: String,
+
+    /// only present on `bounced` events
+    pub bounce: Option<BounceClassification>,
+
+    /// SMTP diagnostic of the recipient server, present on `bounced` and `delivery_delayed` events
+    pub diagnostic_code: Option<String>,
+
+    /// reject reason, failure error, complaint feedback type or delay type
+    pub reason: Option<String>,
+
+    /// clicked link, only present on `clicked` events
+    pub link: Option<String>,
+
+    /// user agent of the recipient, present on `opened`, `clicked` and `complained` events
+    pub user_agent: Option<String>,
+
+    /// present on `opened` and `clicked` events
+    pub ip_address: Option<String>,
+}
+
+impl Routable for NormalizedEmailEvent {
+    fn routing_key(&self) -> String {
+        match &self.recipient {
+            Some(recipient) => format!(
+                "recipient.{}.{}.{}",
+                self.request_uuid,
+                self.kind,
+                recipient_hash(recipient)
+            ),
+            None => format!("recipient.{}.{}", self.request_uuid, self.kind),
+        }
+    }
+}
+
+#[derive(strum_macros::Display, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SnsSubscriptionStatus {
+    /// the topic is not allowlisted for automatic confirmation, the `subscribe_url` must be visited manually
+    PendingConfirmation,
+    Confirmed,
+    ConfirmationFailed,
+    /// the subscription was deleted, the `subscribe_url` can be visited to subscribe again
+    Unsubscribed,
+}
+
+/// informs that the state of a SNS subscription used to deliver SES events to this service changed
+#[derive(Deserialize, Serialize)]
+pub struct SnsSubscriptionEvent {
+    pub timestamp: DateTime<Utc>,
+
+    pub status: SnsSubscriptionStatus,
+
+    pub topic_arn: String,
+
+    pub subscribe_url: String,
+}
+
+impl SnsSubscriptionEvent {
+    pub fn new(
+        status: SnsSubscriptionStatus,
+        topic_arn: String,
+        subscribe_url: String,
+    ) -> SnsSubscriptionEvent {
+        SnsSubscriptionEvent {
+            status,
+            topic_arn,
+            subscribe_url,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+impl Routable for SnsSubscriptionEvent {
+    fn routing_key(&self) -> String {
+        format!("sns.subscription.{}", self.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ses::handler::get_email_event_from_ses_message;
+
+    /// SES event of a email sent to two recipients, `event` holds the event type specific object
+    fn ses_event(event_type: &str, event: &str) -> EmailEvent {
+        let message = format!(
+            r#"{{
+                "eventType": "{}",
+                "mail": {{
+                    "timestamp": "2026-10-01T12:00:00.000Z",
+                    "messageId": "0100018a-message-id",
+                    "sourceArn": null,
+                    "sendingAccountId": "123456789012",
+                    "destination": ["a@example.com", "b@example.com"],
+                    "headersTruncated": false,
+                    "headers": [],
+                    "commonHeaders": {{}},
+                    "tags": {{"request_uuid": ["6f0c4a8e-1b7e-4a55-9a51-7b0b6c1f3a10"]}}
+                }},
+                {}
+            }}"#,
+            event_type, event
+        );
+
+        get_email_event_from_ses_message(&message).unwrap()
+    }
+
+    fn delivery_event() -> EmailEvent {
+        ses_event(
+            "Delivery",
+            r#""delivery": {
+                "timestamp": "2026-10-01T12:00:01.000Z",
+                "processingTimeMillis": 1000,
+                "recipients": ["a@example.com", "b@example.com"],
+                "smtpResponse": "250 ok",
+                "reportingMTA": "a8-1.smtp-out.amazonses.com"
+            }"#,
+        )
+    }
+
+    fn open_event() -> EmailEvent {
+        ses_event(
+            "Open",
+            r#""open": {
+                "timestamp": "2026-10-01T12:05:00.000Z",
+                "ipAddress": "192.0.2.1",
+                "userAgent": "Mozilla/5.0"
+            }"#,
+        )
+    }
+
+    #[test]
+    fn splits_events_with_recipient_lists() {
+        let events = delivery_event().split_by_recipient();
+
+        assert_eq!(events.len(), 2);
+
+        for (event, recipient) in events.iter().zip(["a@example.com", "b@example.com"]) {
+            assert_eq!(event.recipient.as_deref(), Some(recipient));
+            assert_eq!(event.recipients(), vec![recipient.to_owned()]);
+            assert_eq!(
+                event.routing_key(),
+                format!(
+                    "email.6f0c4a8e-1b7e-4a55-9a51-7b0b6c1f3a10.delivery.{}",
+                    recipient_hash(recipient)
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn does_not_drop_events_with_empty_recipient_lists() {
+        let event = ses_event(
+            "Delivery",
+            r#""delivery": {
+                "timestamp": "2026-10-01T12:00:01.000Z",
+                "processingTimeMillis": 1000,
+                "recipients": [],
+                "smtpResponse": "250 ok",
+                "reportingMTA": "a8-1.smtp-out.amazonses.com"
+            }"#,
+        );
+
+        let events = event.split_by_recipient();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].recipient, None);
+        assert_eq!(events[0].routing_key(), event.routing_key());
+
+        let events = event.normalize();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].recipient, None);
+    }
+
+    #[test]
+    fn normalizes_events_with_recipient_lists_per_recipient() {
+        let events = delivery_event().normalize();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].recipient.as_deref(), Some("a@example.com"));
+        assert_eq!(events[1].recipient.as_deref(), Some("b@example.com"));
+    }
+
+    #[test]
+    fn does_not_attribute_events_without_recipient_lists() {
+        let events = open_event().normalize();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].recipient, None);
+        assert_eq!(
+            events[0].routing_key(),
+            "recipient.6f0c4a8e-1b7e-4a55-9a51-7b0b6c1f3a10.opened"
+        );
+
+        let mut single_recipient_event = open_event();
+        single_recipient_event.mail.destination = vec!["a@example.com".to_owned()];
+
+        let events = single_recipient_event.normalize();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].recipient.as_deref(), Some("a@example.com"));
+    }
+
+    #[test]
+    fn does_not_split_events_without_recipient_lists() {
+        let events = open_event().split_by_recipient();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].recipient, None);
+        assert_eq!(
+            events[0].routing_key(),
+            "email.6f0c4a8e-1b7e-4a55-9a51-7b0b6c1f3a10.open"
+        );
     }
 }
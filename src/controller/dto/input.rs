@@ -1,6 +1,9 @@
 //! DTOS for all events and operation inputs accepted by this service
 
-use super::super::validation::{email_vec, rfc_5322_email};
+use super::super::validation::{
+    email_content, email_vec, rfc_5322_email, send_window, template_name,
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use validator::Validate;
@@ -30,8 +33,22 @@ impl EmailRecipient {
     }
 }
 
+/// Priority of the emails of a request, emails with a higher priority are sent first when waiting for the rate limit
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailPriority {
+    /// transactional emails that must not wait behind other emails, eg: password resets
+    High,
+    #[default]
+    Normal,
+    /// bulk emails that can wait for the others, eg: newsletters
+    Low,
+}
+
 #[derive(Debug, Validate, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
+#[validate(schema(function = "send_window"))]
+#[validate(schema(function = "email_content"))]
 pub struct SendEmailIn {
     /// A unique identifier for the email sending request, this is so the client can store this on
     /// his side and use this identifier on future requests, such as getting metrics for this uuid
@@ -51,6 +68,8 @@ pub struct SendEmailIn {
     #[validate(custom = "email_vec")]
     pub reply_to_addresses: Option<Vec<String>>,
 
+    /// Email subject, not used with a `template`
+    #[serde(default)]
     pub subject: String,
 
     pub body_html: Option<String>,
@@ -58,7 +77,47 @@ pub struct SendEmailIn {
     /// Optional email text content: displayed on clients that do not support Html
     pub body_text: Option<String>,
 
+    /// Name of a SES template (see `EmailTemplate`) used instead of `subject`, `bodyHtml` and `bodyText`,
+    /// the template is rendered by SES with the replacements of each recipient
+    #[validate(custom = "template_name")]
+    pub template: Option<String>,
+
     /// If tracking for email events such as clicks and opens should be enabled
     #[serde(default)]
     pub enable_tracking: bool,
+
+    /// When to send the email, requests received before this are stored and sent by the scheduler when due
+    pub send_at: Option<DateTime<Utc>>,
+
+    /// Requests that could not be sent before this are not sent, a `expired` event is published instead
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// Tenant (producer) of the request, used for per tenant rate limits and quotas, always replaced by the AMQP
+    /// `app_id` of the delivery, requests sent through the HTTP API must be of the tenant of their API key
+    pub tenant: Option<String>,
+
+    /// Priority of the emails, see `EmailPriority`
+    #[serde(default)]
+    pub priority: EmailPriority,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelEmailRequestIn {
+    /// uuid of the request to cancel
+    pub uuid: uuid::Uuid,
+}
+
+/// input for the internal `resendEmail` delivery type, enqueued by the resend policy when a resend is due
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResendEmailIn {
+    /// uuid of the request to resend
+    pub uuid: uuid::Uuid,
+
+    /// recipient that bounced transiently
+    pub email: String,
+
+    /// resend attempt of the recipient, starting at 1
+    pub attempt: u32,
 }
@@ -0,0 +1,49 @@
+//! DTOS for the responses of operations exposed by this service
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// response for a email request accepted by the HTTP API, the request is processed asynchronously
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendEmailOut {
+    pub uuid: Uuid,
+}
+
+/// counters of the service since it started
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceStats {
+    /// SES events dropped because the SNS message that carried them was already received
+    pub ses_events_duplicates_dropped: u64,
+}
+
+/// warm-up progress of a sender identity on the current UTC day
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SenderWarmupProgress {
+    /// sender address or domain of the plan
+    pub identity: String,
+
+    /// day of the plan starting from 0, days after the last one mean the warm-up is completed
+    pub day: u32,
+
+    /// amount of days of the plan
+    pub days: u32,
+
+    /// emails the identity can send today, None once the warm-up is completed
+    pub daily_cap: Option<u64>,
+
+    /// emails reserved by the identity today
+    pub used: u64,
+}
+
+/// response for a cancellation, `cancelled` is false if the request was not scheduled nor being sent
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelEmailRequestOut {
+    pub cancelled: bool,
+
+    /// recipients whose email was sent before the cancellation
+    pub sent_recipients: Vec<String>,
+}
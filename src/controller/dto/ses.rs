@@ -22,8 +22,9 @@ pub struct SnsNotification {
     #[serde(rename = "Message")]
     pub message: String,
 
+    /// kept as received since it is part of the signed content
     #[serde(rename = "Timestamp")]
-    pub timestamp: DateTime<Utc>,
+    pub timestamp: String,
 
     #[serde(rename = "SignatureVersion")]
     pub signature_version: String,
@@ -37,6 +38,11 @@ pub struct SnsNotification {
     #[serde(rename = "SubscribeURL")]
     pub subscribe_url: Option<String>,
 
+    /// only present on subscription and unsubscribe confirmations
+    #[serde(rename = "Token")]
+    pub token: Option<String>,
+
+    #[allow(dead_code)]
     #[serde(rename = "UnsubscribeURL")]
     pub unsubscribe_url: Option<String>,
 }
@@ -76,7 +82,7 @@ pub struct SesEvent {
     pub subscription: Option<SubscriptionObj>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MailObj {
     pub timestamp: DateTime<Utc>,
@@ -98,17 +104,17 @@ pub struct MailObj {
     pub tags: HashMap<String, Vec<String>>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Header {
     pub name: String,
 
     pub value: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SendObj {}
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BounceObj {
     pub timestamp: DateTime<Utc>,
 
@@ -128,7 +134,7 @@ pub struct BounceObj {
     pub reporting_mta: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BouncedRecipients {
     pub email_address: String,
@@ -138,7 +144,7 @@ pub struct BouncedRecipients {
     pub diagnostic_code: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ComplaintObj {
     pub complained_recipients: Vec<ComplainedRecipient>,
@@ -156,13 +162,13 @@ pub struct ComplaintObj {
     pub arrival_date: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ComplainedRecipient {
     pub email_address: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DeliveryObj {
     pub timestamp: DateTime<Utc>,
 
@@ -181,12 +187,12 @@ pub struct DeliveryObj {
     pub reporting_mta: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RejectObj {
     pub reason: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OpenObj {
     pub timestamp: DateTime<Utc>,
@@ -196,7 +202,7 @@ pub struct OpenObj {
     pub user_agent: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ClickObj {
     pub timestamp: DateTime<Utc>,
@@ -210,7 +216,7 @@ pub struct ClickObj {
     pub link_tags: Option<HashMap<String, Vec<String>>>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FailureObj {
     pub template_name: String,
@@ -218,7 +224,7 @@ pub struct FailureObj {
     pub error_message: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeliveryDelayObj {
     pub delay_type: String,
@@ -226,7 +232,7 @@ pub struct DeliveryDelayObj {
     pub delayed_recipients: Vec<DelayedRecipient>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DelayedRecipient {
     pub email_address: String,
@@ -236,7 +242,7 @@ pub struct DelayedRecipient {
     pub diagnostic_code: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SubscriptionObj {
     pub contact_list: String,
@@ -250,7 +256,7 @@ pub struct SubscriptionObj {
     pub old_topic_preferences: TopicPreference,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TopicPreference {
     pub unsubscribe_all: bool,
@@ -258,7 +264,7 @@ pub struct TopicPreference {
     pub topic_subscription_status: Vec<TopicSubscriptionStatus>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TopicSubscriptionStatus {
     pub topic_name: String,
@@ -0,0 +1,154 @@
+//! DTOS for querying the state of email requests and their recipients
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(
+    Debug,
+    Deserialize,
+    Serialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum RequestState {
+    /// the request was received and passed validation
+    Received,
+
+    /// the request failed validation and no email was sent
+    Rejected,
+
+    /// the emails for the request are being sent
+    Sending,
+
+    /// all the emails for the request have been fired to SES, this does not mean they were sent successfully
+    Finished,
+
+    /// the request has a `sendAt` in the future and will be sent by the scheduler
+    Scheduled,
+
+    /// the request reached its `expiresAt` before being sent
+    Expired,
+
+    /// the request was cancelled before all its emails were sent
+    Cancelled,
+}
+
+#[derive(
+    Debug,
+    Deserialize,
+    Serialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum RecipientState {
+    Received,
+    Sending,
+    Sent,
+    Failed,
+    Delivered,
+    Opened,
+    Clicked,
+    Bounced,
+    Complained,
+    /// the address is in the suppression list, so no email was sent to it
+    Suppressed,
+    /// the request was cancelled before the email for this recipient was sent
+    Cancelled,
+}
+
+impl RecipientState {
+    /// SES events might arrive out of order (eg: a open before a delivery), a recipient state can
+    /// only be replaced by a state with equal or greater precedence so it never goes backwards
+    pub fn precedence(&self) -> u8 {
+        match self {
+            RecipientState::Received => 0,
+            RecipientState::Sending => 1,
+            RecipientState::Sent => 2,
+            RecipientState::Failed => 3,
+            RecipientState::Delivered => 3,
+            RecipientState::Opened => 4,
+            RecipientState::Clicked => 5,
+            RecipientState::Bounced => 6,
+            RecipientState::Complained => 7,
+            RecipientState::Suppressed => 3,
+            RecipientState::Cancelled => 3,
+        }
+    }
+
+    /// if the email for the recipient was accepted by SES
+    pub fn was_sent(&self) -> bool {
+        matches!(
+            self,
+            RecipientState::Sent
+                | RecipientState::Delivered
+                | RecipientState::Opened
+                | RecipientState::Clicked
+                | RecipientState::Bounced
+                | RecipientState::Complained
+        )
+    }
+
+    /// the recipient state for a SES event type (snake case), None if the event does not change it
+    pub fn from_ses_event_type(event_type: &str) -> Option<RecipientState> {
+        match event_type {
+            "send" => Some(RecipientState::Sent),
+            "delivery" => Some(RecipientState::Delivered),
+            "open" => Some(RecipientState::Opened),
+            "click" => Some(RecipientState::Clicked),
+            "bounce" => Some(RecipientState::Bounced),
+            "complaint" => Some(RecipientState::Complained),
+            "reject" | "failure" => Some(RecipientState::Failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipientStatus {
+    pub email: String,
+
+    pub state: RecipientState,
+
+    /// id of the SES message that contains this recipient, only present once the email is sent
+    pub ses_message_id: Option<String>,
+
+    /// error returned by SES when the email for this recipient failed to be sent
+    pub error: Option<String>,
+
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestStatus {
+    pub request_uuid: Uuid,
+
+    pub state: RequestState,
+
+    pub created_at: DateTime<Utc>,
+
+    pub updated_at: DateTime<Utc>,
+
+    pub recipients: Vec<RecipientStatus>,
+}
+
+/// input for the `getRequestStatus` delivery type, the reply is a `RequestStatus` or `null` if the request is unknown
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GetRequestStatusIn {
+    pub uuid: Uuid,
+}
@@ -0,0 +1,131 @@
+//! DTOS for the suppression list, addresses that must not receive emails
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(
+    Debug,
+    Deserialize,
+    Serialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SuppressionReason {
+    /// the address permanently bounced
+    Bounce,
+
+    /// the recipient marked a email as spam
+    Complaint,
+
+    /// added by a admin operation
+    Manual,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SuppressedAddress {
+    /// trimmed and lowercased email address
+    pub email: String,
+
+    pub reason: SuppressionReason,
+
+    /// bounce diagnostic, complaint feedback type or a note of who added the address
+    pub detail: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+}
+
+impl SuppressedAddress {
+    pub fn new(
+        email: &str,
+        reason: SuppressionReason,
+        detail: Option<String>,
+    ) -> SuppressedAddress {
+        SuppressedAddress {
+            email: normalize_email(email),
+            reason,
+            detail,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// addresses are compared case insensitively
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// input for the `addSuppression` delivery type and `POST /suppressions`, the reply is the `SuppressedAddress`
+#[derive(Debug, Validate, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AddSuppressionIn {
+    #[validate(email)]
+    pub email: String,
+
+    pub detail: Option<String>,
+}
+
+/// input for the `removeSuppression` delivery type, the reply is a `RemoveSuppressionOut`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveSuppressionIn {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveSuppressionOut {
+    /// false if the address was not suppressed
+    pub removed: bool,
+}
+
+#[derive(
+    Debug,
+    Deserialize,
+    Serialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SesSuppressionSyncMode {
+    /// adds the addresses of the SES account suppression list to the local list
+    Import,
+
+    /// adds the addresses of the local list to the SES account suppression list
+    Export,
+
+    /// imports and exports, so both lists contain the same addresses
+    Reconcile,
+}
+
+/// input for the `syncSesSuppressions` delivery type, the reply is a `SesSuppressionSyncReport`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SesSuppressionSyncIn {
+    pub mode: SesSuppressionSyncMode,
+
+    /// if addresses missing on the source list are deleted from the target list, ignored when reconciling
+    #[serde(default)]
+    pub prune: bool,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SesSuppressionSyncReport {
+    pub imported: usize,
+    pub exported: usize,
+    pub removed_locally: usize,
+    pub removed_from_ses: usize,
+}
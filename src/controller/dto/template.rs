@@ -0,0 +1,74 @@
+//! DTOS for the SES email templates managed by this service
+//!
+//! see: https://docs.aws.amazon.com/ses/latest/dg/send-personalized-email-api.html
+
+use super::super::validation::template_name;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use validator::Validate;
+
+/// input for the `createEmailTemplate` and `updateEmailTemplate` delivery types, `POST /templates` and
+/// `PUT /templates/{name}`, replacements are written as `{{name}}` on the subject, html and text
+///
+/// the reply is the `EmailTemplate`, or null if it already exists (create) or does not exist (update)
+#[derive(Debug, Validate, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailTemplate {
+    /// ignored by the HTTP routes with the name in the path
+    #[validate(custom = "template_name")]
+    #[serde(default)]
+    pub name: String,
+
+    pub subject: String,
+
+    pub html: Option<String>,
+
+    /// displayed on clients that do not support html
+    pub text: Option<String>,
+}
+
+/// a template of the SES account, without its content
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailTemplateMetadata {
+    pub name: String,
+
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// input for the `getEmailTemplate` and `deleteEmailTemplate` delivery types, the replies are
+/// a `EmailTemplate` (null if it does not exist) and a `DeleteEmailTemplateOut` respectively
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailTemplateNameIn {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteEmailTemplateOut {
+    /// false if the template did not exist
+    pub deleted: bool,
+}
+
+/// input for the `testRenderEmailTemplate` delivery type and `POST /templates/{name}/render`,
+/// the reply is a `TestRenderEmailTemplateOut`, or null if the template does not exist
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TestRenderEmailTemplateIn {
+    /// ignored by the HTTP route, which has the name in the path
+    #[serde(default)]
+    pub name: String,
+
+    /// replacements used to render the template, like the replacements of a recipient
+    #[serde(default)]
+    pub replacements: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TestRenderEmailTemplateOut {
+    /// the complete MIME message SES would send, including the headers and the rendered subject and parts
+    pub rendered_template: String,
+}
@@ -1,4 +1,9 @@
-use crate::{mail::mailer::Mailer, queue::server};
+use crate::{
+    mail::{mailer::Mailer, tenant_quota::TenantQuotas, warmup::SenderWarmup},
+    queue::server,
+    ses::{identities::SenderIdentities, templates::SesTemplates},
+    storage::repository::Repository,
+};
 use lapin::{
     message::Delivery,
     options::{BasicAckOptions, BasicNackOptions},
@@ -12,12 +17,33 @@ use super::routes::default;
 #[derive(Debug)]
 pub struct Router {
     pub server: Arc<server::Server>,
-    pub mailer: Mailer,
+    pub mailer: Arc<Mailer>,
+    pub repository: Arc<dyn Repository>,
+    pub tenant_quotas: TenantQuotas,
+    pub warmup: Arc<SenderWarmup>,
+    pub sender_identities: Arc<SenderIdentities>,
+    pub templates: Arc<SesTemplates>,
 }
 
 impl Router {
-    pub fn new(server: Arc<server::Server>, mailer: Mailer) -> Router {
-        Router { server, mailer }
+    pub fn new(
+        server: Arc<server::Server>,
+        mailer: Arc<Mailer>,
+        repository: Arc<dyn Repository>,
+        tenant_quotas: TenantQuotas,
+        warmup: Arc<SenderWarmup>,
+        sender_identities: Arc<SenderIdentities>,
+        templates: Arc<SesTemplates>,
+    ) -> Router {
+        Router {
+            server,
+            mailer,
+            repository,
+            tenant_quotas,
+            warmup,
+            sender_identities,
+            templates,
+        }
     }
 
     #[tracing::instrument(skip(self))]
@@ -25,7 +51,27 @@ impl Router {
         let delivery_type = get_delivery_type(&delivery);
 
         let handler_res = match delivery_type.as_str() {
-            "sendEmail" => self.send_email(delivery).await,
+            "sendEmail" => {
+                // held before being acked, so they are redelivered if the service stops while paused
+                self.server.until_sending_resumed().await;
+                self.send_email(delivery).await
+            }
+            "resendEmail" => {
+                self.server.until_sending_resumed().await;
+                self.resend_email(delivery).await
+            }
+            "cancelEmailRequest" => self.cancel_email_request(delivery).await,
+            "getRequestStatus" => self.get_request_status(delivery).await,
+            "listSuppressions" => self.list_suppressions(delivery).await,
+            "addSuppression" => self.add_suppression(delivery).await,
+            "removeSuppression" => self.remove_suppression(delivery).await,
+            "syncSesSuppressions" => self.sync_ses_suppressions(delivery).await,
+            "listEmailTemplates" => self.list_email_templates(delivery).await,
+            "createEmailTemplate" => self.create_email_template(delivery).await,
+            "updateEmailTemplate" => self.update_email_template(delivery).await,
+            "getEmailTemplate" => self.get_email_template(delivery).await,
+            "deleteEmailTemplate" => self.delete_email_template(delivery).await,
+            "testRenderEmailTemplate" => self.test_render_email_template(delivery).await,
             _ => default::handle_delivery_without_corresponding_rpc(delivery).await,
         };
 
@@ -47,6 +93,7 @@ fn get_delivery_type(delivery: &Delivery) -> String {
         .to_string()
 }
 
+#[allow(clippy::needless_borrow)]
 pub async fn ack_delivery(delivery: &Delivery) -> Result<(), String> {
     delivery
         .ack(BasicAckOptions::default())
@@ -54,6 +101,7 @@ pub async fn ack_delivery(delivery: &Delivery) -> Result<(), String> {
         .or(Err(create_ack_nack_error_string(&delivery)))
 }
 
+#[allow(clippy::needless_borrow)]
 pub async fn nack_delivery(delivery: &Delivery) -> Result<(), String> {
     delivery
         .nack(BasicNackOptions::default())
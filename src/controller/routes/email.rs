@@ -1,3 +1,4 @@
+use chrono::Utc;
 use lapin::message::Delivery;
 use uuid::Uuid;
 use validator::Validate;
@@ -5,30 +6,162 @@ use validator::Validate;
 use crate::{
     controller::{
         dto::{
-            events::{EmailRequestFinishedEvent, EmailSendingReceivedEvent},
+            events::{
+                EmailRequestExpiredEvent, EmailRequestFinishedEvent, EmailSendingReceivedEvent,
+            },
             input,
+            status::RequestState,
         },
         router::{ack_delivery, Router},
     },
-    mail::mailer::SendEmailOptions,
+    mail::mailer::{SendEmailOptions, SendEmailsOutcome},
 };
+use tracing::error;
 
 impl Router {
+    #[allow(clippy::bind_instead_of_map)]
     #[tracing::instrument(skip(self))]
     pub async fn send_email(&self, delivery: Delivery) -> Result<(), String> {
         ack_delivery(&delivery).await?;
 
-        let send_email_in = serde_json::from_slice::<input::SendEmailIn>(&delivery.data)
+        let mut send_email_in = serde_json::from_slice::<input::SendEmailIn>(&delivery.data)
             .or_else(|e| Err(format!("parse error: {:#?}", e)))?;
 
+        // the body field is ignored so producers cannot claim the quotas and senders of other tenants, the
+        // tenant is stored with the request so scheduled requests and resends keep the one of the original delivery
+        send_email_in.tenant = delivery
+            .properties
+            .app_id()
+            .as_ref()
+            .map(|tenant| tenant.to_string().trim().to_lowercase());
+
         let uuid = send_email_in.uuid.unwrap_or(Uuid::new_v4());
 
-        if let Err(e) = send_email_in.validate() {
+        let rejection_reason = match send_email_in.validate() {
+            Err(e) => Some(e.to_string()),
+            Ok(_) => self.sender_identities.get_rejection_reason(
+                send_email_in.tenant.as_deref(),
+                send_email_in.sender.as_deref(),
+            ),
+        };
+
+        if let Some(reason) = rejection_reason {
+            self.reject_request(uuid, send_email_in, reason.clone())
+                .await?;
+
+            return Err(reason);
+        }
+
+        let now = Utc::now();
+
+        // scheduled requests are enqueued again by the scheduler when due, so they may also expire while waiting
+        if let Some(expires_at) = send_email_in.expires_at.filter(|e| *e <= now) {
+            if let Err(e) = self
+                .repository
+                .save_request(uuid, &send_email_in, RequestState::Expired)
+                .await
+            {
+                error!("failed to store expired request: {}", e)
+            }
+
+            self.server
+                .publish_as_json(EmailRequestExpiredEvent::new(uuid, expires_at))
+                .await?;
+
+            return Ok(());
+        }
+
+        if send_email_in.send_at.is_some_and(|s| s > now) {
+            if let Err(e) = self
+                .repository
+                .save_request(uuid, &send_email_in, RequestState::Scheduled)
+                .await
+            {
+                // without storing it the scheduler cannot send it, so the request is lost
+                return Err(format!("failed to store scheduled request: {}", e));
+            }
+
             self.server
-                .publish_as_json(EmailSendingReceivedEvent::rejected(uuid, send_email_in))
+                .publish_as_json(EmailSendingReceivedEvent::scheduled(uuid, send_email_in))
                 .await?;
 
-            return Err(e.to_string());
+            return Ok(());
+        }
+
+        // filtered before reserving, so suppressed recipients do not count on the tenant quota and the warm-up cap
+        let suppressed = self
+            .mailer
+            .get_suppressed_recipients(&send_email_in.to)
+            .await;
+
+        let emails = send_email_in
+            .to
+            .iter()
+            .filter(|r| !suppressed.contains(&r.email))
+            .count() as u64;
+
+        let tenant = send_email_in.tenant.clone().unwrap_or_default();
+
+        let over_quota_event = self
+            .tenant_quotas
+            .reserve(&tenant, uuid, emails)
+            .await
+            .unwrap_or_else(|e| {
+                error!("failed to reserve tenant quota, sending anyway: {}", e);
+                None
+            });
+
+        if let Some(over_quota_event) = over_quota_event {
+            if let Err(e) = self
+                .repository
+                .save_request(uuid, &send_email_in, RequestState::Rejected)
+                .await
+            {
+                error!("failed to store rejected request: {}", e)
+            }
+
+            self.server.publish_as_json(over_quota_event).await?;
+
+            return Ok(());
+        }
+
+        let deferred_event = self
+            .warmup
+            .reserve(send_email_in.sender.as_deref(), uuid, emails)
+            .await
+            .unwrap_or_else(|e| {
+                error!("failed to reserve warm-up emails, sending anyway: {}", e);
+                None
+            });
+
+        // deferred requests are sent by the scheduler on the day they fit, when they are checked against its cap
+        // and reserved again on the tenant quota
+        if let Some(deferred_event) = deferred_event {
+            send_email_in.send_at = Some(deferred_event.send_at);
+
+            if let Err(e) = self.tenant_quotas.release(&tenant, uuid).await {
+                error!("failed to release tenant quota of deferred request: {}", e)
+            }
+
+            if let Err(e) = self
+                .repository
+                .save_request(uuid, &send_email_in, RequestState::Scheduled)
+                .await
+            {
+                return Err(format!("failed to store deferred request: {}", e));
+            }
+
+            self.server.publish_as_json(deferred_event).await?;
+
+            return Ok(());
+        }
+
+        if let Err(e) = self
+            .repository
+            .save_request(uuid, &send_email_in, RequestState::Received)
+            .await
+        {
+            error!("failed to store received request: {}", e)
         }
 
         self.server
@@ -38,7 +171,22 @@ impl Router {
             ))
             .await?;
 
+        if let Err(e) = self
+            .repository
+            .set_request_state(uuid, RequestState::Sending)
+            .await
+        {
+            error!("failed to store request state: {}", e)
+        }
+
+        send_email_in.to.retain(|r| !suppressed.contains(&r.email));
+
         self.mailer
+            .skip_suppressed_recipients(uuid, suppressed)
+            .await;
+
+        let outcome = self
+            .mailer
             .send_emails(SendEmailOptions {
                 uuid,
                 to: send_email_in.to,
@@ -46,15 +194,217 @@ impl Router {
                 subject: send_email_in.subject,
                 body_text: send_email_in.body_text,
                 body_html: send_email_in.body_html,
+                template: send_email_in.template,
                 track_events: send_email_in.enable_tracking,
                 reply_to_addresses: send_email_in.reply_to_addresses,
+                tenant: send_email_in.tenant,
+                priority: send_email_in.priority,
             })
             .await?;
 
+        // the cancellation stores the request state and publishes its own event
+        if outcome == SendEmailsOutcome::Cancelled {
+            return Ok(());
+        }
+
+        if let Err(e) = self
+            .repository
+            .set_request_state(uuid, RequestState::Finished)
+            .await
+        {
+            error!("failed to store request state: {}", e)
+        }
+
         self.server
             .publish_as_json(EmailRequestFinishedEvent::new(uuid))
             .await?;
 
         Ok(())
     }
+
+    /// stores the request as rejected and publishes the rejection with its reason
+    async fn reject_request(
+        &self,
+        uuid: Uuid,
+        send_email_in: input::SendEmailIn,
+        reason: String,
+    ) -> Result<(), String> {
+        if let Err(e) = self
+            .repository
+            .save_request(uuid, &send_email_in, RequestState::Rejected)
+            .await
+        {
+            error!("failed to store rejected request: {}", e)
+        }
+
+        self.server
+            .publish_as_json(EmailSendingReceivedEvent::rejected(
+                uuid,
+                send_email_in,
+                reason,
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    /// resends the email of a stored request to a recipient that bounced transiently, checking the request
+    /// the same way as `send_email` except for its content, which was already validated when it was received
+    #[tracing::instrument(skip(self))]
+    pub async fn resend_email(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let input = serde_json::from_slice::<input::ResendEmailIn>(&delivery.data)
+            .map_err(|e| format!("parse error: {:#?}", e))?;
+
+        let uuid = input.uuid;
+
+        let request = self
+            .repository
+            .get_request(uuid)
+            .await?
+            .ok_or(format!("request {} not found", uuid))?;
+
+        let cancelled = self
+            .repository
+            .get_request_status(uuid)
+            .await?
+            .is_some_and(|status| status.state == RequestState::Cancelled);
+
+        if cancelled {
+            println!("[RESEND] skipping resend of cancelled request {}", uuid);
+            return Ok(());
+        }
+
+        if request.expires_at.is_some_and(|e| e <= Utc::now()) {
+            println!("[RESEND] skipping resend of expired request {}", uuid);
+            return Ok(());
+        }
+
+        if let Some(reason) = self
+            .sender_identities
+            .get_rejection_reason(request.tenant.as_deref(), request.sender.as_deref())
+        {
+            return Err(format!("skipping resend of request {}: {}", uuid, reason));
+        }
+
+        let recipient = request
+            .to
+            .iter()
+            .find(|r| r.email.eq_ignore_ascii_case(&input.email))
+            .ok_or(format!(
+                "recipient {} not found on request {}",
+                input.email, uuid
+            ))?
+            .clone();
+
+        let suppressed = self
+            .mailer
+            .get_suppressed_recipients(std::slice::from_ref(&recipient))
+            .await;
+
+        if !suppressed.is_empty() {
+            self.mailer
+                .skip_suppressed_recipients(uuid, suppressed)
+                .await;
+            return Ok(());
+        }
+
+        // reservations are counted once per uuid, so each resend attempt reserves under its own uuid
+        let reservation_uuid = Uuid::new_v5(
+            &uuid,
+            format!("{}:{}", recipient.email, input.attempt).as_bytes(),
+        );
+
+        let tenant = request.tenant.clone().unwrap_or_default();
+
+        let over_quota_event = self
+            .tenant_quotas
+            .reserve(&tenant, reservation_uuid, 1)
+            .await
+            .unwrap_or_else(|e| {
+                error!("failed to reserve tenant quota, sending anyway: {}", e);
+                None
+            });
+
+        if let Some(mut over_quota_event) = over_quota_event {
+            over_quota_event.request_uuid = uuid;
+            self.server.publish_as_json(over_quota_event).await?;
+
+            return Ok(());
+        }
+
+        let deferred_event = self
+            .warmup
+            .reserve(request.sender.as_deref(), reservation_uuid, 1)
+            .await
+            .unwrap_or_else(|e| {
+                error!("failed to reserve warm-up emails, sending anyway: {}", e);
+                None
+            });
+
+        if let Some(mut deferred_event) = deferred_event {
+            if let Err(e) = self.tenant_quotas.release(&tenant, reservation_uuid).await {
+                error!("failed to release tenant quota of deferred resend: {}", e)
+            }
+
+            self.repository
+                .schedule_resend(
+                    uuid,
+                    &recipient.email,
+                    input.attempt,
+                    deferred_event.send_at,
+                    None,
+                )
+                .await
+                .map_err(|e| format!("failed to store deferred resend: {}", e))?;
+
+            deferred_event.request_uuid = uuid;
+            self.server.publish_as_json(deferred_event).await?;
+
+            return Ok(());
+        }
+
+        println!(
+            "[RESEND] resending request {} to {}, attempt {}",
+            uuid, recipient.email, input.attempt
+        );
+
+        self.repository
+            .reset_recipient_state(uuid, &recipient.email)
+            .await?;
+
+        // the content is rendered from the stored request, so the recipient gets exactly the first email
+        self.mailer
+            .send_emails(SendEmailOptions {
+                uuid,
+                to: vec![recipient],
+                from: request.sender,
+                subject: request.subject,
+                body_text: request.body_text,
+                body_html: request.body_html,
+                template: request.template,
+                track_events: request.enable_tracking,
+                reply_to_addresses: request.reply_to_addresses,
+                tenant: request.tenant,
+                priority: request.priority,
+            })
+            .await
+            .map(|_| ())
+    }
+
+    /// cancels a scheduled or in progress request, replying with the recipients that were already sent
+    #[tracing::instrument(skip(self))]
+    pub async fn cancel_email_request(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let input = serde_json::from_slice::<input::CancelEmailRequestIn>(&delivery.data)
+            .map_err(|e| format!("parse error: {:#?}", e))?;
+
+        let output = self.mailer.cancel_request(input.uuid).await?;
+
+        self.server.reply_as_json(&delivery, output).await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,23 @@
+use lapin::message::Delivery;
+
+use crate::controller::{
+    dto::status::GetRequestStatusIn,
+    router::{ack_delivery, Router},
+};
+
+impl Router {
+    /// replies to the delivery `reply_to` queue with the status of the requested uuid, or `null` if its unknown
+    #[tracing::instrument(skip(self))]
+    pub async fn get_request_status(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let input = serde_json::from_slice::<GetRequestStatusIn>(&delivery.data)
+            .map_err(|e| format!("parse error: {:#?}", e))?;
+
+        let request_status = self.repository.get_request_status(input.uuid).await?;
+
+        self.server.reply_as_json(&delivery, request_status).await?;
+
+        Ok(())
+    }
+}
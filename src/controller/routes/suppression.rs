@@ -0,0 +1,82 @@
+use lapin::message::Delivery;
+use validator::Validate;
+
+use crate::{
+    controller::{
+        dto::suppression::{
+            AddSuppressionIn, RemoveSuppressionIn, RemoveSuppressionOut, SesSuppressionSyncIn,
+            SuppressedAddress, SuppressionReason,
+        },
+        router::{ack_delivery, Router},
+    },
+    ses::suppression_sync::SesSuppressionSync,
+};
+
+impl Router {
+    /// replies to the delivery `reply_to` queue with every suppressed address
+    #[tracing::instrument(skip(self))]
+    pub async fn list_suppressions(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let suppressions = self.repository.list_suppressions().await?;
+
+        self.server.reply_as_json(&delivery, suppressions).await?;
+
+        Ok(())
+    }
+
+    /// suppresses the address manually, replying with the suppressed address
+    #[tracing::instrument(skip(self))]
+    pub async fn add_suppression(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let input = serde_json::from_slice::<AddSuppressionIn>(&delivery.data)
+            .map_err(|e| format!("parse error: {:#?}", e))?;
+
+        input.validate().map_err(|e| e.to_string())?;
+
+        let suppression =
+            SuppressedAddress::new(&input.email, SuppressionReason::Manual, input.detail);
+
+        self.repository.add_suppression(&suppression).await?;
+
+        self.server.reply_as_json(&delivery, suppression).await?;
+
+        Ok(())
+    }
+
+    /// removes the address from the suppression list, so it can receive emails again
+    #[tracing::instrument(skip(self))]
+    pub async fn remove_suppression(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let input = serde_json::from_slice::<RemoveSuppressionIn>(&delivery.data)
+            .map_err(|e| format!("parse error: {:#?}", e))?;
+
+        let removed = self.repository.remove_suppression(&input.email).await?;
+
+        self.server
+            .reply_as_json(&delivery, RemoveSuppressionOut { removed })
+            .await?;
+
+        Ok(())
+    }
+
+    /// synchronizes the local suppression list with the SES account suppression list, replying with the sync report
+    #[tracing::instrument(skip(self))]
+    pub async fn sync_ses_suppressions(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let input = serde_json::from_slice::<SesSuppressionSyncIn>(&delivery.data)
+            .map_err(|e| format!("parse error: {:#?}", e))?;
+
+        let report =
+            SesSuppressionSync::new(self.mailer.aws_client.clone(), self.repository.clone())
+                .run(input.mode, input.prune)
+                .await?;
+
+        self.server.reply_as_json(&delivery, report).await?;
+
+        Ok(())
+    }
+}
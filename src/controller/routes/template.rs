@@ -0,0 +1,112 @@
+use lapin::message::Delivery;
+use validator::Validate;
+
+use crate::controller::{
+    dto::template::{
+        DeleteEmailTemplateOut, EmailTemplate, EmailTemplateNameIn, TestRenderEmailTemplateIn,
+        TestRenderEmailTemplateOut,
+    },
+    router::{ack_delivery, Router},
+};
+
+impl Router {
+    /// replies to the delivery `reply_to` queue with every SES template, without their content
+    #[tracing::instrument(skip(self))]
+    pub async fn list_email_templates(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let templates = self.templates.list().await?;
+
+        self.server.reply_as_json(&delivery, templates).await?;
+
+        Ok(())
+    }
+
+    /// creates a SES template, replying with the template or null if it already exists
+    #[tracing::instrument(skip(self))]
+    pub async fn create_email_template(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let input = serde_json::from_slice::<EmailTemplate>(&delivery.data)
+            .map_err(|e| format!("parse error: {:#?}", e))?;
+
+        input.validate().map_err(|e| e.to_string())?;
+
+        let created = self.templates.create(&input).await?;
+
+        self.server
+            .reply_as_json(&delivery, created.then_some(input))
+            .await?;
+
+        Ok(())
+    }
+
+    /// replaces the content of a SES template, replying with the template or null if it does not exist
+    #[tracing::instrument(skip(self))]
+    pub async fn update_email_template(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let input = serde_json::from_slice::<EmailTemplate>(&delivery.data)
+            .map_err(|e| format!("parse error: {:#?}", e))?;
+
+        input.validate().map_err(|e| e.to_string())?;
+
+        let updated = self.templates.update(&input).await?;
+
+        self.server
+            .reply_as_json(&delivery, updated.then_some(input))
+            .await?;
+
+        Ok(())
+    }
+
+    /// replies with the SES template or null if it does not exist
+    #[tracing::instrument(skip(self))]
+    pub async fn get_email_template(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let input = serde_json::from_slice::<EmailTemplateNameIn>(&delivery.data)
+            .map_err(|e| format!("parse error: {:#?}", e))?;
+
+        let template = self.templates.get(&input.name).await?;
+
+        self.server.reply_as_json(&delivery, template).await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn delete_email_template(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let input = serde_json::from_slice::<EmailTemplateNameIn>(&delivery.data)
+            .map_err(|e| format!("parse error: {:#?}", e))?;
+
+        let deleted = self.templates.delete(&input.name).await?;
+
+        self.server
+            .reply_as_json(&delivery, DeleteEmailTemplateOut { deleted })
+            .await?;
+
+        Ok(())
+    }
+
+    /// renders a SES template with the given replacements, replying with the rendered message or null if it does not exist
+    #[tracing::instrument(skip(self))]
+    pub async fn test_render_email_template(&self, delivery: Delivery) -> Result<(), String> {
+        ack_delivery(&delivery).await?;
+
+        let input = serde_json::from_slice::<TestRenderEmailTemplateIn>(&delivery.data)
+            .map_err(|e| format!("parse error: {:#?}", e))?;
+
+        let rendered = self
+            .templates
+            .test_render(&input.name, &input.replacements)
+            .await?
+            .map(|rendered_template| TestRenderEmailTemplateOut { rendered_template });
+
+        self.server.reply_as_json(&delivery, rendered).await?;
+
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use super::dto::input::SendEmailIn;
 use email_format::Email;
 use validator::{validate_email, ValidationError};
 
@@ -11,6 +12,7 @@ pub fn email_vec(emails: &Vec<String>) -> Result<(), ValidationError> {
     Ok(())
 }
 
+#[allow(clippy::ptr_arg)]
 pub fn rfc_5322_email(email: &String) -> Result<(), ValidationError> {
     if Email::new(email.as_str(), "Wed, 5 Jan 2015 15:13:05 +1300").is_err() {
         return Err(ValidationError::new("sender is not a valid RFC5322 email"));
@@ -18,3 +20,98 @@ pub fn rfc_5322_email(email: &String) -> Result<(), ValidationError> {
 
     Ok(())
 }
+
+/// the address of a sender that may include a display name, eg: `Example <news@example.com>`, lowercased
+pub fn get_sender_address(sender: &str) -> String {
+    let address = match sender.rsplit_once('<') {
+        Some((_, address)) => address.trim_end().trim_end_matches('>'),
+        None => sender,
+    };
+
+    address.trim().to_lowercase()
+}
+
+/// SES template names can only contain alphanumeric characters, underscores and dashes
+pub fn template_name(name: &str) -> Result<(), ValidationError> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+    if name.is_empty() || name.len() > 64 || !valid_chars {
+        return Err(ValidationError::new(
+            "template name must have 1 to 64 alphanumeric characters, underscores or dashes",
+        ));
+    }
+
+    Ok(())
+}
+
+/// only the template combinations are checked, so requests without a template are accepted as before templates
+pub fn email_content(request: &SendEmailIn) -> Result<(), ValidationError> {
+    let has_content =
+        !request.subject.is_empty() || request.body_html.is_some() || request.body_text.is_some();
+
+    if request.template.is_some() && has_content {
+        return Err(ValidationError::new(
+            "subject, bodyHtml and bodyText cannot be used with a template",
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn send_window(request: &SendEmailIn) -> Result<(), ValidationError> {
+    if let (Some(send_at), Some(expires_at)) = (request.send_at, request.expires_at) {
+        if expires_at <= send_at {
+            return Err(ValidationError::new("expiresAt must be after sendAt"));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn send_email_in(body: serde_json::Value) -> SendEmailIn {
+        let mut request = json!({ "to": [{ "email": "user@example.com" }] });
+        request
+            .as_object_mut()
+            .unwrap()
+            .extend(body.as_object().unwrap().clone());
+
+        serde_json::from_value(request).unwrap()
+    }
+
+    #[test]
+    fn email_content_accepts_any_content_without_template() {
+        for content in [
+            json!({ "subject": "" }),
+            json!({ "subject": "hi" }),
+            json!({ "subject": "hi", "bodyText": "hi" }),
+            json!({ "subject": "hi", "bodyHtml": "<p>hi</p>" }),
+        ] {
+            assert!(
+                email_content(&send_email_in(content.clone())).is_ok(),
+                "{}",
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn email_content_rejects_content_with_template() {
+        let request = send_email_in(json!({ "template": "welcome", "subject": "hi" }));
+        assert!(email_content(&request).is_err());
+
+        let request = send_email_in(json!({ "template": "welcome", "bodyText": "hi" }));
+        assert!(email_content(&request).is_err());
+    }
+
+    #[test]
+    fn email_content_accepts_template_alone() {
+        assert!(email_content(&send_email_in(json!({ "template": "welcome" }))).is_ok());
+    }
+}
@@ -1,106 +1,86 @@
 use crate::{
     config,
     controller::dto::{
-        events::{Email, EmailEvent},
-        ses::{SesEvent, SnsNotification},
+        events::{SnsSubscriptionEvent, SnsSubscriptionStatus},
+        input::SendEmailIn,
+        output::{CancelEmailRequestOut, SendEmailOut, SenderWarmupProgress, ServiceStats},
+        ses::SnsNotification,
+        status::RequestStatus,
+        suppression::{AddSuppressionIn, SuppressedAddress, SuppressionReason},
+        template::{
+            EmailTemplate, EmailTemplateMetadata, TestRenderEmailTemplateIn,
+            TestRenderEmailTemplateOut,
+        },
     },
-    mail::mailer::MAIL_REQUEST_UUID_TAG_NAME,
+    http::sns::{self, SnsVerifier},
+    mail::{mailer::Mailer, warmup::SenderWarmup},
     queue::server::Server,
+    ses::{
+        handler::{self, SesEventHandler},
+        templates::SesTemplates,
+    },
+    storage::repository::Repository,
 };
 use axum::{
-    extract::State,
-    http::{Request, StatusCode},
+    extract::{DefaultBodyLimit, Extension, Path, State},
+    http::{header, HeaderMap, Request, StatusCode},
     middleware::{self, Next},
-    response::Response,
-    routing::post,
-    Router,
-};
-use convert_case::{Case, Casing};
-use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr},
-    sync::Arc,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
 };
+use axum_server::tls_rustls::RustlsConfig;
+use ring::{constant_time, digest};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tokio::time;
+use tower_http::timeout::TimeoutLayer;
 use tracing::error;
+use uuid::Uuid;
+use validator::Validate;
+
+/// timeout for requests made to SNS, such as downloading signing certificates and confirming subscriptions
+static SNS_HTTP_TIMEOUT_SECS: u64 = 5;
 
-fn get_email_event_from_json_str(body: &String) -> Result<EmailEvent, String> {
-    let sns_notification = serde_json::from_str::<SnsNotification>(&body).or_else(|e| {
-        Err(format!(
-            "failed to parse request body to SnsNotification: {}",
-            e.to_string()
-        ))
+async fn handle_ses_event(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<String, StatusCode> {
+    let sns_notification = serde_json::from_str::<SnsNotification>(&body).map_err(|e| {
+        error!("failed to parse request body to SnsNotification: {}", e);
+        StatusCode::BAD_REQUEST
     })?;
 
-    if let Some(sub_url) = sns_notification.subscribe_url {
-        let is_subscription_confirmation = sns_notification
-            .notification_type
-            .eq("SubscriptionConfirmation");
+    check_sns_arn(&state, &headers, &sns_notification)?;
 
-        if is_subscription_confirmation {
-            println!("[WEB] SNS subscription confirmation link: {}", sub_url);
-            return Err("request is subscription confirmation event, not a email event".to_owned());
+    if let Some(sns_verifier) = &state.sns_verifier {
+        if let Err(e) = sns_verifier.verify(&sns_notification).await {
+            error!("SNS message verification failed: {}", e);
+            return Err(StatusCode::BAD_REQUEST);
         }
     }
 
-    let ses_evt = serde_json::from_str::<SesEvent>(&sns_notification.message).or_else(|e| {
-        Err(format!(
-            "failed to parse request body to SesEvent: {}",
-            e.to_string()
-        ))
-    })?;
+    let is_subscription_message = matches!(
+        sns_notification.notification_type.as_str(),
+        "SubscriptionConfirmation" | "UnsubscribeConfirmation"
+    );
 
-    let request_uuid = ses_evt
-        .mail
-        .tags
-        .get(MAIL_REQUEST_UUID_TAG_NAME)
-        .ok_or(format!(
-            "required tag: {} not present on mail tags",
-            MAIL_REQUEST_UUID_TAG_NAME
-        ))?
-        .first()
-        .ok_or(format!(
-            "required tag: {} is present but is empty",
-            MAIL_REQUEST_UUID_TAG_NAME
-        ))?
-        .to_owned();
-
-    let event_type = ses_evt
-        .event_type
-        .or(ses_evt.notification_type)
-        .ok_or("failed to get event type from ses event")?
-        .to_case(Case::Snake);
-
-    let err_msg = format!("object for event of type: {} not present", event_type);
-
-    let event = match event_type.as_str() {
-        "send" => Email::send(ses_evt.send.ok_or(err_msg)?),
-        "open" => Email::open(ses_evt.open.ok_or(err_msg)?),
-        "click" => Email::click(ses_evt.click.ok_or(err_msg)?),
-        "bounce" => Email::bounce(ses_evt.bounce.ok_or(err_msg)?),
-        "reject" => Email::reject(ses_evt.reject.ok_or(err_msg)?),
-        "failure" => Email::failure(ses_evt.failure.ok_or(err_msg)?),
-        "delivery" => Email::delivery(ses_evt.delivery.ok_or(err_msg)?),
-        "complaint" => Email::complaint(ses_evt.complaint.ok_or(err_msg)?),
-        "subscription" => Email::subscription(ses_evt.subscription.ok_or(err_msg)?),
-        "delivery_delay" => Email::delivery_delay(ses_evt.delivery_delay.ok_or(err_msg)?),
-        _ => return Err(format!("unknown event type: {}", event_type)),
-    };
+    if is_subscription_message {
+        return handle_sns_subscription_message(&state, sns_notification).await;
+    }
 
-    Ok(EmailEvent {
-        event,
-        event_type,
-        request_uuid,
-        mail: ses_evt.mail,
-    })
-}
+    let sns_message_id = sns_notification.message_id.clone();
 
-async fn handle_ses_event(
-    State(state): State<AppState>,
-    body: String,
-) -> Result<String, StatusCode> {
-    match get_email_event_from_json_str(&body) {
+    match handler::get_email_event_from_sns_notification(sns_notification) {
         Ok(email_event) => {
-            if let Err(publish_error) = state.queue_server.publish_as_json(email_event).await {
-                error!("ses event publishing failed: {}", publish_error)
+            // SNS retries deliveries that fail with a 5xx status, so the event is not lost
+            if let Err(publish_error) = state
+                .ses_event_handler
+                .handle(email_event, Some(&sns_message_id))
+                .await
+            {
+                error!("ses event publishing failed: {}", publish_error);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
             }
 
             Ok("event handled correctly".to_owned())
@@ -113,55 +93,532 @@ async fn handle_ses_event(
     }
 }
 
+/// confirms subscriptions to allowlisted topics automatically and publishes a event informing the subscription state
+async fn handle_sns_subscription_message(
+    state: &AppState,
+    sns_notification: SnsNotification,
+) -> Result<String, StatusCode> {
+    let topic_arn = sns_notification.topic_arn;
+    let subscribe_url = sns_notification.subscribe_url.ok_or_else(|| {
+        error!("SNS subscription message without SubscribeURL");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let event = if sns_notification.notification_type == "UnsubscribeConfirmation" {
+        println!("[WEB] SNS subscription to topic {} removed", topic_arn);
+        SnsSubscriptionEvent::new(
+            SnsSubscriptionStatus::Unsubscribed,
+            topic_arn,
+            subscribe_url,
+        )
+    } else if state.sns_auto_confirm_topic_arns.contains(&topic_arn) {
+        match sns::confirm_subscription(&state.http_client, &subscribe_url).await {
+            Ok(_) => {
+                println!("[WEB] SNS subscription to topic {} confirmed", topic_arn);
+                SnsSubscriptionEvent::new(
+                    SnsSubscriptionStatus::Confirmed,
+                    topic_arn,
+                    subscribe_url,
+                )
+            }
+            Err(e) => {
+                error!("failed to confirm SNS subscription: {}", e);
+                SnsSubscriptionEvent::new(
+                    SnsSubscriptionStatus::ConfirmationFailed,
+                    topic_arn,
+                    subscribe_url,
+                )
+            }
+        }
+    } else {
+        println!(
+            "[WEB] SNS subscription confirmation link: {}",
+            subscribe_url
+        );
+        SnsSubscriptionEvent::new(
+            SnsSubscriptionStatus::PendingConfirmation,
+            topic_arn,
+            subscribe_url,
+        )
+    };
+
+    if let Err(publish_error) = state.queue_server.publish_as_json(event).await {
+        error!(
+            "sns subscription event publishing failed: {}",
+            publish_error
+        )
+    }
+
+    Ok("subscription message handled correctly".to_owned())
+}
+
+async fn get_request_status(
+    State(state): State<AppState>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<RequestStatus>, StatusCode> {
+    state
+        .repository
+        .get_request_status(uuid)
+        .await
+        .map_err(|e| {
+            error!("failed to get request status: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// cancels a scheduled or in progress request, responds with a conflict if the request is not
+/// scheduled nor being sent (eg: it already finished or is unknown)
+async fn cancel_email_request(
+    State(state): State<AppState>,
+    Path(uuid): Path<Uuid>,
+) -> Result<(StatusCode, Json<CancelEmailRequestOut>), StatusCode> {
+    let output = state.mailer.cancel_request(uuid).await.map_err(|e| {
+        error!("failed to cancel request: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let status = if output.cancelled {
+        StatusCode::OK
+    } else {
+        StatusCode::CONFLICT
+    };
+
+    Ok((status, Json(output)))
+}
+
+async fn list_suppressions(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<SuppressedAddress>>, StatusCode> {
+    state
+        .repository
+        .list_suppressions()
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("failed to list suppressions: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+async fn add_suppression(
+    State(state): State<AppState>,
+    Json(input): Json<AddSuppressionIn>,
+) -> Response {
+    if let Err(validation_errors) = input.validate() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(validation_errors)).into_response();
+    }
+
+    let suppression = SuppressedAddress::new(&input.email, SuppressionReason::Manual, input.detail);
+
+    if let Err(e) = state.repository.add_suppression(&suppression).await {
+        error!("failed to add suppression: {}", e);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    (StatusCode::CREATED, Json(suppression)).into_response()
+}
+
+async fn remove_suppression(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> StatusCode {
+    match state.repository.remove_suppression(&email).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            error!("failed to remove suppression: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+async fn list_email_templates(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<EmailTemplateMetadata>>, StatusCode> {
+    state.templates.list().await.map(Json).map_err(|e| {
+        error!("failed to list templates: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// creates a SES template, responds with a conflict if a template with the same name already exists
+async fn create_email_template(
+    State(state): State<AppState>,
+    Json(input): Json<EmailTemplate>,
+) -> Response {
+    if let Err(validation_errors) = input.validate() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(validation_errors)).into_response();
+    }
+
+    match state.templates.create(&input).await {
+        Ok(true) => (StatusCode::CREATED, Json(input)).into_response(),
+        Ok(false) => StatusCode::CONFLICT.into_response(),
+        Err(e) => {
+            error!("failed to create template: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn update_email_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(mut input): Json<EmailTemplate>,
+) -> Response {
+    input.name = name;
+
+    if let Err(validation_errors) = input.validate() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(validation_errors)).into_response();
+    }
+
+    match state.templates.update(&input).await {
+        Ok(true) => Json(input).into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            error!("failed to update template: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn get_email_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<EmailTemplate>, StatusCode> {
+    state
+        .templates
+        .get(&name)
+        .await
+        .map_err(|e| {
+            error!("failed to get template: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn delete_email_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> StatusCode {
+    match state.templates.delete(&name).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            error!("failed to delete template: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// renders a SES template with the replacements in the body, SES fails to render templates missing a replacement
+async fn test_render_email_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(input): Json<TestRenderEmailTemplateIn>,
+) -> Result<Json<TestRenderEmailTemplateOut>, StatusCode> {
+    state
+        .templates
+        .test_render(&name, &input.replacements)
+        .await
+        .map_err(|e| {
+            error!("failed to render template: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map(|rendered_template| Json(TestRenderEmailTemplateOut { rendered_template }))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn get_stats(State(state): State<AppState>) -> Json<ServiceStats> {
+    Json(ServiceStats {
+        ses_events_duplicates_dropped: state.ses_event_handler.duplicates_dropped(),
+    })
+}
+
+/// warm-up progress of every sender identity with a plan on the current UTC day
+async fn get_warmup_progress(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<SenderWarmupProgress>>, StatusCode> {
+    state.warmup.get_progress().await.map(Json).map_err(|e| {
+        error!("failed to get warm-up progress: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// validates the email request and enqueues it to be processed by `Router::send_email`, as a request of
+/// the tenant of the API key, requests with a `tenant` other than the one of the key are forbidden
+async fn send_email(
+    State(state): State<AppState>,
+    Extension(ApiKeyTenant(tenant)): Extension<ApiKeyTenant>,
+    Json(mut send_email_in): Json<SendEmailIn>,
+) -> Response {
+    let other_tenant = send_email_in
+        .tenant
+        .as_ref()
+        .is_some_and(|t| t.trim().to_lowercase() != tenant);
+
+    if other_tenant {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    send_email_in.tenant = Some(tenant.clone());
+
+    if let Err(validation_errors) = send_email_in.validate() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(validation_errors)).into_response();
+    }
+
+    let uuid = *send_email_in.uuid.get_or_insert_with(Uuid::new_v4);
+
+    if let Err(e) = state
+        .queue_server
+        .enqueue_as_json("sendEmail", Some(&tenant), send_email_in)
+        .await
+    {
+        error!("failed to enqueue email request: {}", e);
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+
+    (StatusCode::ACCEPTED, Json(SendEmailOut { uuid })).into_response()
+}
+
 #[derive(Clone)]
 struct AppState {
     queue_server: Arc<Server>,
+    repository: Arc<dyn Repository>,
+    mailer: Arc<Mailer>,
+    warmup: Arc<SenderWarmup>,
+    templates: Arc<SesTemplates>,
+    ses_event_handler: Arc<SesEventHandler>,
     aws_email_sns_subscription_arn: Option<String>,
+    sns_verifier: Option<Arc<SnsVerifier>>,
+    sns_auto_confirm_topic_arns: Vec<String>,
+    http_client: reqwest::Client,
+
+    /// SHA-256 digests of the keys with their tenant, compared in constant time with the digest of the bearer token
+    api_keys: Vec<(digest::Digest, String)>,
 }
 
-/// forbids any incoming requests where the x-amz-sns-subscription-arn
-/// does not match the `aws_email_sns_subscription_arn` in the application state,
-/// in order to avoid potentially malicious requests from registering fake events
-async fn check_sns_arn<T>(
+/// tenant of the API key of a request, set by `check_api_key`
+#[derive(Clone)]
+struct ApiKeyTenant(String);
+
+/// forbids any incoming requests without a `Authorization: Bearer <key>` header
+/// where key is one of the `api_keys` in the application state, passing the tenant of the key to the handler
+async fn check_api_key<T>(
     State(state): State<AppState>,
-    req: Request<T>,
+    mut req: Request<T>,
     nxt: Next<T>,
 ) -> Result<Response, StatusCode> {
-    if state.aws_email_sns_subscription_arn.is_none() {
-        return Ok(nxt.run(req).await);
-    }
+    let bearer_token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "));
 
-    if let Some(arn_header) = req.headers().get("x-amz-sns-subscription-arn") {
-        let sns_header_matches = arn_header.to_str().unwrap_or("").eq(state
-            .aws_email_sns_subscription_arn
-            .unwrap_or("".to_owned())
-            .as_str());
+    let Some(token) = bearer_token else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
 
-        if sns_header_matches {
-            return Ok(nxt.run(req).await);
-        }
-    }
+    let token_digest = digest::digest(&digest::SHA256, token.as_bytes());
+
+    // every key is compared, so the response time does not tell which key the token resembles
+    let tenant = state
+        .api_keys
+        .iter()
+        .fold(
+            None,
+            |matched, (key_digest, tenant)| match constant_time::verify_slices_are_equal(
+                key_digest.as_ref(),
+                token_digest.as_ref(),
+            ) {
+                Ok(_) => Some(tenant.to_owned()),
+                Err(_) => matched,
+            },
+        );
+
+    let Some(tenant) = tenant else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
 
-    Err(StatusCode::BAD_REQUEST)
+    req.extensions_mut().insert(ApiKeyTenant(tenant));
+
+    Ok(nxt.run(req).await)
 }
 
-pub async fn serve(cfg: &config::AppConfig, server: Arc<Server>) {
+/// forbids notifications whose x-amz-sns-subscription-arn header does not match the `aws_email_sns_subscription_arn`
+/// in the application state, in order to avoid potentially malicious requests from registering fake events.
+/// Subscription messages are sent before the subscription exists, so their topic is compared with the
+/// topic of the subscription (the ARN without the subscription id) instead
+fn check_sns_arn(
+    state: &AppState,
+    headers: &HeaderMap,
+    sns_notification: &SnsNotification,
+) -> Result<(), StatusCode> {
+    let Some(subscription_arn) = &state.aws_email_sns_subscription_arn else {
+        return Ok(());
+    };
+
+    let matches = match sns_notification.notification_type.as_str() {
+        "SubscriptionConfirmation" | "UnsubscribeConfirmation" => subscription_arn
+            .rsplit_once(':')
+            .is_some_and(|(topic_arn, _)| topic_arn == sns_notification.topic_arn),
+        _ => headers
+            .get("x-amz-sns-subscription-arn")
+            .is_some_and(|header| header.to_str().unwrap_or("") == subscription_arn),
+    };
+
+    if matches {
+        Ok(())
+    } else {
+        Err(StatusCode::BAD_REQUEST)
+    }
+}
+
+pub async fn serve(
+    cfg: &config::AppConfig,
+    server: Arc<Server>,
+    repository: Arc<dyn Repository>,
+    mailer: Arc<Mailer>,
+    warmup: Arc<SenderWarmup>,
+    templates: Arc<SesTemplates>,
+    ses_event_handler: Arc<SesEventHandler>,
+) {
+    let http_client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(SNS_HTTP_TIMEOUT_SECS))
+        .build()
+        .expect("[WEB] failed to create HTTP client");
+
     let state = AppState {
         queue_server: server,
+        repository,
+        mailer,
+        warmup,
+        templates,
+        ses_event_handler,
         aws_email_sns_subscription_arn: cfg.aws_sns_tracking_subscription_arn.clone(),
+        api_keys: cfg
+            .http_api_keys
+            .iter()
+            .flatten()
+            .map(|entry| {
+                let (tenant, key) = entry.split_once('=').unwrap_or_else(|| {
+                    panic!("[WEB] invalid API key, expected <tenant>=<key>: {}", entry)
+                });
+
+                (
+                    digest::digest(&digest::SHA256, key.trim().as_bytes()),
+                    tenant.trim().to_lowercase(),
+                )
+            })
+            .collect(),
+        sns_verifier: if cfg.aws_sns_verify_signatures {
+            let sns_verifier = SnsVerifier::new(cfg, http_client.clone())
+                .unwrap_or_else(|e| panic!("[WEB] failed to create SNS verifier: {}", e));
+
+            Some(Arc::new(sns_verifier))
+        } else {
+            None
+        },
+        sns_auto_confirm_topic_arns: cfg
+            .aws_sns_auto_confirm_topic_arns
+            .clone()
+            .unwrap_or_default(),
+        http_client,
     };
 
+    if state.api_keys.is_empty() {
+        println!("[WEB] HTTP_API_KEYS not set, API routes will reject every request");
+    }
+
+    let sns_routes = Router::new().route("/ses-events", post(handle_ses_event));
+
+    let api_routes = Router::new()
+        .route("/emails", post(send_email))
+        .route("/requests/:uuid", get(get_request_status))
+        .route("/requests/:uuid/cancel", post(cancel_email_request))
+        .route("/stats", get(get_stats))
+        .route("/warmup", get(get_warmup_progress))
+        .route(
+            "/suppressions",
+            get(list_suppressions).post(add_suppression),
+        )
+        .route("/suppressions/:email", delete(remove_suppression))
+        .route(
+            "/templates",
+            get(list_email_templates).post(create_email_template),
+        )
+        .route(
+            "/templates/:name",
+            get(get_email_template)
+                .put(update_email_template)
+                .delete(delete_email_template),
+        )
+        .route("/templates/:name/render", post(test_render_email_template))
+        .route_layer(middleware::from_fn_with_state(state.clone(), check_api_key));
+
     let app = Router::new()
-        .route("/ses-events", post(handle_ses_event))
-        .route_layer(middleware::from_fn_with_state(state.clone(), check_sns_arn))
+        .merge(sns_routes)
+        .merge(api_routes)
+        .layer(DefaultBodyLimit::max(cfg.http_max_body_bytes))
+        .layer(TimeoutLayer::new(Duration::from_secs(
+            cfg.http_request_timeout_secs,
+        )))
         .with_state(state);
 
-    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), cfg.http_port);
-    println!("[WEB] listening on {}", addr);
+    let addr = SocketAddr::new(cfg.http_host, cfg.http_port);
 
-    axum::Server::try_bind(&addr)
-        .expect(format!("[WEB] failed to get address {}", addr).as_str())
-        .serve(app.into_make_service())
-        .await
-        .expect(format!("[WEB] failed to serve app on address {}", addr).as_str())
+    let serve_result = match (&cfg.http_tls_cert_path, &cfg.http_tls_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let tls_config = RustlsConfig::from_pem_file(cert_path, key_path)
+                .await
+                .unwrap_or_else(|e| panic!("[WEB] failed to load TLS certificate: {}", e));
+
+            if cfg.http_tls_reload_interval_secs > 0 {
+                tokio::spawn(reload_tls_config(
+                    tls_config.clone(),
+                    cert_path.to_owned(),
+                    key_path.to_owned(),
+                    cfg.http_tls_reload_interval_secs,
+                ));
+            }
+
+            println!("[WEB] listening on {} with TLS", addr);
+
+            axum_server::bind_rustls(addr, tls_config)
+                .serve(app.into_make_service())
+                .await
+        }
+        (None, None) => {
+            println!("[WEB] listening on {}", addr);
+
+            axum_server::bind(addr).serve(app.into_make_service()).await
+        }
+        _ => panic!("[WEB] HTTP_TLS_CERT_PATH and HTTP_TLS_KEY_PATH must be set together"),
+    };
+
+    serve_result.unwrap_or_else(|e| panic!("[WEB] failed to serve app on address {}: {}", addr, e))
+}
+
+/// periodically reloads the TLS certificate and key from disk, so renewed certificates are used without a restart
+async fn reload_tls_config(
+    tls_config: RustlsConfig,
+    cert_path: String,
+    key_path: String,
+    interval_secs: u64,
+) {
+    let mut interval = time::interval(Duration::from_secs(interval_secs));
+
+    // the first tick completes immediately and the certificate was just loaded
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        match tls_config.reload_from_pem_file(&cert_path, &key_path).await {
+            Ok(_) => println!("[WEB] TLS certificate reloaded"),
+            Err(e) => error!("failed to reload TLS certificate: {}", e),
+        }
+    }
 }
@@ -0,0 +1,370 @@
+//! Verification of the signatures of SNS messages, so only messages really sent by AWS are accepted
+//!
+//! see: https://docs.aws.amazon.com/sns/latest/dg/sns-verify-signature-of-message.html
+
+use crate::{config, controller::dto::ses::SnsNotification};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Url;
+use ring::signature::{self, RsaParameters, UnparsedPublicKey};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::RwLock;
+use x509_parser::pem::parse_x509_pem;
+
+/// DER encoded RSA public key of a signing certificate, only trusted until the certificate expires
+#[derive(Debug, Clone)]
+struct SigningCertKey {
+    public_key: Arc<Vec<u8>>,
+    not_after: DateTime<Utc>,
+}
+
+impl SigningCertKey {
+    fn is_expired(&self) -> bool {
+        self.not_after <= Utc::now()
+    }
+}
+
+#[derive(Debug)]
+pub struct SnsVerifier {
+    http_client: reqwest::Client,
+
+    /// keys of the already downloaded signing certificates, by certificate url
+    public_keys: RwLock<HashMap<String, SigningCertKey>>,
+
+    /// key of a pre-provisioned certificate, if present its used for every message instead of `SigningCertURL`
+    local_public_key: Option<SigningCertKey>,
+
+    /// messages with a `Timestamp` older than this are rejected to prevent replay attacks
+    max_message_age: Duration,
+}
+
+/// extracts the public key of a PEM encoded X509 certificate, failing if the certificate is expired
+fn public_key_from_pem(pem: &[u8]) -> Result<SigningCertKey, String> {
+    let (_, pem) = parse_x509_pem(pem).map_err(|e| format!("invalid PEM certificate: {}", e))?;
+
+    let cert = pem
+        .parse_x509()
+        .map_err(|e| format!("invalid X509 certificate: {}", e))?;
+
+    if !cert.validity().is_valid() {
+        return Err("signing certificate is expired or not yet valid".to_owned());
+    }
+
+    let not_after = DateTime::from_timestamp(cert.validity().not_after.timestamp(), 0)
+        .ok_or("invalid signing certificate expiration")?;
+
+    Ok(SigningCertKey {
+        public_key: Arc::new(cert.public_key().subject_public_key.data.to_vec()),
+        not_after,
+    })
+}
+
+/// only urls served over HTTPS by a SNS domain, eg: `sns.us-east-1.amazonaws.com`, are trusted
+fn parse_sns_url(raw_url: &str) -> Result<Url, String> {
+    let url = Url::parse(raw_url).map_err(|e| format!("invalid SNS url: {}", e))?;
+
+    let host = url.host_str().unwrap_or("");
+
+    let region = host
+        .strip_prefix("sns.")
+        .and_then(|h| {
+            h.strip_suffix(".amazonaws.com")
+                .or(h.strip_suffix(".amazonaws.com.cn"))
+        })
+        .unwrap_or("");
+
+    let is_sns_host = !region.is_empty()
+        && region
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+    if url.scheme() != "https" || !is_sns_host {
+        return Err(format!("untrusted SNS url: {}", raw_url));
+    }
+
+    Ok(url)
+}
+
+/// visits the `SubscribeURL` of a `SubscriptionConfirmation` message, confirming the subscription
+pub async fn confirm_subscription(
+    http_client: &reqwest::Client,
+    subscribe_url: &str,
+) -> Result<(), String> {
+    let url = parse_sns_url(subscribe_url)?;
+
+    if url
+        .query_pairs()
+        .all(|(k, v)| k != "Action" || v != "ConfirmSubscription")
+    {
+        return Err(format!(
+            "not a subscription confirmation url: {}",
+            subscribe_url
+        ));
+    }
+
+    http_client
+        .get(url)
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| format!("failed to confirm SNS subscription: {}", e))?;
+
+    Ok(())
+}
+
+/// builds the string signed by SNS, the fields and their order depend on the message type
+fn string_to_sign(notification: &SnsNotification) -> Result<String, String> {
+    let mut fields = vec![
+        ("Message", Some(&notification.message)),
+        ("MessageId", Some(&notification.message_id)),
+    ];
+
+    match notification.notification_type.as_str() {
+        "Notification" => {
+            fields.push(("Subject", notification.subject.as_ref()));
+            fields.push(("Timestamp", Some(&notification.timestamp)));
+        }
+        "SubscriptionConfirmation" | "UnsubscribeConfirmation" => {
+            fields.push(("SubscribeURL", notification.subscribe_url.as_ref()));
+            fields.push(("Timestamp", Some(&notification.timestamp)));
+            fields.push(("Token", notification.token.as_ref()));
+        }
+        other => return Err(format!("unknown SNS message type: {}", other)),
+    }
+
+    fields.push(("TopicArn", Some(&notification.topic_arn)));
+    fields.push(("Type", Some(&notification.notification_type)));
+
+    Ok(fields
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|v| format!("{}\n{}\n", name, v)))
+        .collect())
+}
+
+impl SnsVerifier {
+    pub fn new(
+        cfg: &config::AppConfig,
+        http_client: reqwest::Client,
+    ) -> Result<SnsVerifier, String> {
+        let local_public_key = match &cfg.aws_sns_signing_cert_path {
+            Some(path) => {
+                let pem = std::fs::read(path)
+                    .map_err(|e| format!("failed to read SNS signing certificate: {}", e))?;
+
+                Some(public_key_from_pem(&pem)?)
+            }
+            None => None,
+        };
+
+        Ok(SnsVerifier {
+            http_client,
+            local_public_key,
+            public_keys: RwLock::new(HashMap::new()),
+            max_message_age: Duration::seconds(cfg.aws_sns_max_message_age_secs as i64),
+        })
+    }
+
+    async fn get_public_key(&self, cert_url: &str) -> Result<Arc<Vec<u8>>, String> {
+        if let Some(key) = &self.local_public_key {
+            if key.is_expired() {
+                return Err("local SNS signing certificate is expired".to_owned());
+            }
+
+            return Ok(key.public_key.clone());
+        }
+
+        // expired certificates are downloaded again, AWS serves the renewed certificate on the same url
+        if let Some(key) = self.public_keys.read().await.get(cert_url) {
+            if !key.is_expired() {
+                return Ok(key.public_key.clone());
+            }
+        }
+
+        let url = parse_sns_url(cert_url)?;
+
+        if !url.path().ends_with(".pem") {
+            return Err(format!("untrusted SigningCertURL: {}", cert_url));
+        }
+
+        let pem = self
+            .http_client
+            .get(url)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| format!("failed to download SNS signing certificate: {}", e))?
+            .bytes()
+            .await
+            .map_err(|e| format!("failed to download SNS signing certificate: {}", e))?;
+
+        let key = public_key_from_pem(&pem)?;
+
+        self.public_keys
+            .write()
+            .await
+            .insert(cert_url.to_owned(), key.clone());
+
+        Ok(key.public_key)
+    }
+
+    /// checks the message is recent and its `Signature` matches the content signed by the `SigningCertURL` certificate
+    pub async fn verify(&self, notification: &SnsNotification) -> Result<(), String> {
+        let timestamp = DateTime::parse_from_rfc3339(&notification.timestamp)
+            .map_err(|e| format!("invalid SNS message Timestamp: {}", e))?;
+
+        if Utc::now().signed_duration_since(timestamp) > self.max_message_age {
+            return Err(format!(
+                "stale SNS message, Timestamp: {}",
+                notification.timestamp
+            ));
+        }
+
+        let algorithm: &'static RsaParameters = match notification.signature_version.as_str() {
+            "1" => &signature::RSA_PKCS1_2048_8192_SHA1_FOR_LEGACY_USE_ONLY,
+            "2" => &signature::RSA_PKCS1_2048_8192_SHA256,
+            v => return Err(format!("unsupported SNS SignatureVersion: {}", v)),
+        };
+
+        let signature = BASE64
+            .decode(&notification.signature)
+            .map_err(|e| format!("invalid SNS message Signature encoding: {}", e))?;
+
+        let public_key = self.get_public_key(&notification.signing_cert_url).await?;
+
+        UnparsedPublicKey::new(algorithm, public_key.as_slice())
+            .verify(string_to_sign(notification)?.as_bytes(), &signature)
+            .map_err(|_| "SNS message Signature does not match its content".to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::SecondsFormat;
+    use rsa::{
+        pkcs1v15::SigningKey,
+        pkcs8::DecodePrivateKey,
+        sha2::Sha256,
+        signature::{SignatureEncoding, Signer},
+        RsaPrivateKey,
+    };
+    use sha1::Sha1;
+
+    /// self signed certificate generated for the tests, valid for 100 years
+    static SIGNING_CERT: &[u8] = include_bytes!("testdata/sns_signing_cert.pem");
+    static SIGNING_KEY: &str = include_str!("testdata/sns_signing_key.pem");
+
+    fn verifier(local_cert: Option<&[u8]>) -> SnsVerifier {
+        SnsVerifier {
+            http_client: reqwest::Client::new(),
+            public_keys: RwLock::new(HashMap::new()),
+            local_public_key: local_cert.map(|pem| public_key_from_pem(pem).unwrap()),
+            max_message_age: Duration::minutes(5),
+        }
+    }
+
+    /// a notification signed with the test key
+    fn signed_notification(signature_version: &str, timestamp: DateTime<Utc>) -> SnsNotification {
+        let mut notification = SnsNotification {
+            notification_type: "Notification".to_owned(),
+            message_id: "22b80b92-fdea-4c2c-8f9d-bdfb0c7bf324".to_owned(),
+            topic_arn: "arn:aws:sns:us-east-1:123456789012:ses-events".to_owned(),
+            subject: None,
+            message: r#"{"eventType":"Delivery"}"#.to_owned(),
+            timestamp: timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            signature_version: signature_version.to_owned(),
+            signature: String::new(),
+            signing_cert_url:
+                "https://sns.us-east-1.amazonaws.com/SimpleNotificationService-test.pem".to_owned(),
+            subscribe_url: None,
+            token: None,
+            unsubscribe_url: None,
+        };
+
+        let key = RsaPrivateKey::from_pkcs8_pem(SIGNING_KEY).unwrap();
+        let content = string_to_sign(&notification).unwrap();
+
+        let signature = match signature_version {
+            "1" => SigningKey::<Sha1>::new(key)
+                .sign(content.as_bytes())
+                .to_vec(),
+            _ => SigningKey::<Sha256>::new(key)
+                .sign(content.as_bytes())
+                .to_vec(),
+        };
+
+        notification.signature = BASE64.encode(signature);
+        notification
+    }
+
+    #[tokio::test]
+    async fn verifies_signed_messages() {
+        let verifier = verifier(Some(SIGNING_CERT));
+
+        for version in ["1", "2"] {
+            let notification = signed_notification(version, Utc::now());
+            assert_eq!(verifier.verify(&notification).await, Ok(()));
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_messages() {
+        let verifier = verifier(Some(SIGNING_CERT));
+
+        let mut notification = signed_notification("2", Utc::now());
+        notification.message = r#"{"eventType":"Bounce"}"#.to_owned();
+        assert!(verifier.verify(&notification).await.is_err());
+
+        let mut notification = signed_notification("2", Utc::now());
+        let mut signature = BASE64.decode(&notification.signature).unwrap();
+        signature[0] ^= 1;
+        notification.signature = BASE64.encode(signature);
+        assert!(verifier.verify(&notification).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_certificates_outside_sns() {
+        let verifier = verifier(None);
+
+        for cert_url in [
+            "https://example.com/SimpleNotificationService-test.pem",
+            "https://sns.us-east-1.amazonaws.com.example.com/SimpleNotificationService-test.pem",
+            "http://sns.us-east-1.amazonaws.com/SimpleNotificationService-test.pem",
+        ] {
+            let mut notification = signed_notification("2", Utc::now());
+            notification.signing_cert_url = cert_url.to_owned();
+
+            let error = verifier.verify(&notification).await.unwrap_err();
+            assert!(error.starts_with("untrusted SNS url"), "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_stale_messages() {
+        let verifier = verifier(Some(SIGNING_CERT));
+        let notification = signed_notification("2", Utc::now() - Duration::hours(1));
+
+        let error = verifier.verify(&notification).await.unwrap_err();
+        assert!(error.starts_with("stale SNS message"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_signature_versions() {
+        let verifier = verifier(Some(SIGNING_CERT));
+        let notification = signed_notification("3", Utc::now());
+
+        let error = verifier.verify(&notification).await.unwrap_err();
+        assert!(
+            error.starts_with("unsupported SNS SignatureVersion"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn reads_certificate_expiration() {
+        let key = public_key_from_pem(SIGNING_CERT).unwrap();
+        assert!(!key.is_expired());
+        assert!(key.not_after > Utc::now() + Duration::days(365 * 50));
+    }
+}
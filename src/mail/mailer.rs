@@ -1,31 +1,51 @@
 use crate::{
     config,
-    controller::dto::{events::EmailSendingErrorEvent, input},
+    controller::dto::{
+        events::{EmailRequestCancelledEvent, EmailSendingErrorEvent, EmailSuppressedEvent},
+        input::{self, EmailPriority},
+        output::CancelEmailRequestOut,
+        status::{RecipientState, RequestState},
+        template::EmailTemplate,
+    },
+    mail::{
+        rate_limiter::{new_rate_limiter, SendRateLimiter},
+        throttle::{SendThrottle, ThrottleGroup},
+    },
     queue::{self, server},
+    ses::{client::new_ses_client, templates::SesTemplates},
+    storage::repository::Repository,
 };
 use aws_sdk_sesv2::{
     client::customize::Response,
-    config::Region,
     error::SdkError,
-    operation::send_email::{builders::SendEmailFluentBuilder, SendEmailError, SendEmailOutput},
-    types::{Body, Content, Destination, EmailContent, Message, MessageTag},
+    operation::{
+        send_bulk_email::{
+            builders::SendBulkEmailFluentBuilder, SendBulkEmailError, SendBulkEmailOutput,
+        },
+        send_email::{builders::SendEmailFluentBuilder, SendEmailError, SendEmailOutput},
+    },
+    types::{
+        Body, BulkEmailContent, BulkEmailEntry, BulkEmailStatus, Content, Destination,
+        EmailContent, Message, MessageTag, ReplacementEmailContent, ReplacementTemplate, Template,
+    },
     Client,
 };
-use governor::{
-    clock::{QuantaClock, QuantaInstant},
-    middleware::NoOpMiddleware,
-    state::{InMemoryState, NotKeyed},
-    Quota,
-};
 use handlebars::Handlebars;
-use std::{num::NonZeroU32, sync::Arc, thread, time};
-use tokio::task::JoinSet;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread, time,
+};
+use tokio::{sync::watch, task::JoinSet};
 use tracing::{log::error, Instrument};
 use uuid::Uuid;
 
 /// see: https://docs.aws.amazon.com/ses/latest/APIReference/API_SendEmail.html
 static MAX_RECIPIENTS_PER_SEND_EMAIL_OP: usize = 50;
 
+/// see: https://docs.aws.amazon.com/ses/latest/APIReference-V2/API_SendBulkEmail.html
+static MAX_ENTRIES_PER_SEND_BULK_EMAIL_OP: usize = 50;
+
 static MAX_EMAIL_RETRY_ATTEMPT: u8 = 4;
 
 static RETRY_ATTEMPTS_INTERVAL: u8 = 5;
@@ -42,8 +62,16 @@ pub struct SendEmailOptions {
     pub body_text: Option<String>,
     pub body_html: Option<String>,
 
+    /// Name of a SES template used instead of the subject and bodies, rendered by SES with the recipients replacements
+    pub template: Option<String>,
+
     pub reply_to_addresses: Option<Vec<String>>,
 
+    /// Tenant (producer) of the request, emails of different tenants are sent in turns
+    pub tenant: Option<String>,
+
+    pub priority: EmailPriority,
+
     /// Uuid of the email request, used to publish error/finished events when all the deliveries for the request finish
     pub uuid: Uuid,
 
@@ -57,27 +85,126 @@ pub struct SendEmailOptions {
     pub track_events: bool,
 }
 
-type RateLimiter =
-    governor::RateLimiter<NotKeyed, InMemoryState, QuantaClock, NoOpMiddleware<QuantaInstant>>;
+/// how a `send_emails` call ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendEmailsOutcome {
+    /// every email was fired to SES
+    Finished,
+
+    /// the request was cancelled with `Mailer::cancel_request`, some recipients may have been sent
+    Cancelled,
+}
 
 #[derive(Debug)]
 pub struct Mailer {
     pub server: Arc<server::Server>,
+    pub repository: Arc<dyn Repository>,
     pub aws_client: Client,
-    pub rate_limiter: Arc<RateLimiter>,
+    pub rate_limiter: Arc<dyn SendRateLimiter>,
+    pub throttle: Arc<SendThrottle>,
     pub default_sender: String,
     pub aws_ses_tracking_config_set: String,
+
+    /// if recipients with replacements are sent with SendBulkEmail and a SES template, see `aws_ses_bulk_templates`
+    pub bulk_templates: bool,
+
+    /// SES templates client, also creates the templates of requests sent with `bulk_templates`
+    pub templates: Arc<SesTemplates>,
+
+    /// cancellation signal of the requests being sent, the channel closes once every task of the request stopped
+    in_progress: Mutex<HashMap<Uuid, Arc<watch::Sender<bool>>>>,
+}
+
+/// creates the SES template for the emails of a request, its replacements are rendered by SES
+async fn put_template(templates: &SesTemplates, template: &EmailTemplate) -> Result<(), String> {
+    if let Ok(true) = templates.create(template).await {
+        return Ok(());
+    }
+
+    // the create may have timed out after the template was created, in which case it exists with the same content
+    match templates.update(template).await? {
+        true => Ok(()),
+        false => Err(format!("SES template {} was not created", template.name)),
+    }
+}
+
+/// sets the recipients to sending and waits for the throttle, returning false if the request was cancelled meanwhile,
+/// `bulk` operations take a account permit per recipient, see `SendThrottle::until_bulk_ready`
+async fn wait_for_throttle(
+    throttle: &SendThrottle,
+    group: &ThrottleGroup,
+    request_uuid: uuid::Uuid,
+    recipients: &[String],
+    repository: &Arc<dyn Repository>,
+    cancelled: &mut watch::Receiver<bool>,
+    bulk: bool,
+) -> bool {
+    if let Err(e) = repository
+        .set_recipients_state(
+            request_uuid,
+            recipients,
+            RecipientState::Sending,
+            None,
+            None,
+        )
+        .await
+    {
+        error!("failed to store recipients state: {}", e)
+    }
+
+    // chunks waiting for the rate limiters are not sent if the request is cancelled meanwhile
+    let was_cancelled = tokio::select! {
+        biased;
+
+        _ = cancelled.wait_for(|cancelled| *cancelled) => true,
+        _ = throttle.until_ready(group, recipients), if !bulk => false,
+        _ = throttle.until_bulk_ready(group, recipients), if bulk => false,
+    };
+
+    if was_cancelled {
+        if let Err(e) = repository
+            .set_recipients_state(
+                request_uuid,
+                recipients,
+                RecipientState::Cancelled,
+                None,
+                None,
+            )
+            .await
+        {
+            error!("failed to store recipients state: {}", e)
+        }
+    }
+
+    !was_cancelled
 }
 
+#[allow(clippy::too_many_arguments, clippy::nonminimal_bool)]
 #[tracing::instrument]
 async fn send_with_rate_limiter(
-    rate_limiter: Arc<RateLimiter>,
+    throttle: Arc<SendThrottle>,
+    group: ThrottleGroup,
     send_email_op: SendEmailFluentBuilder,
     request_uuid: uuid::Uuid,
     recipients: Vec<String>,
     server: Arc<queue::server::Server>,
-) -> Result<SendEmailOutput, SdkError<SendEmailError, Response>> {
-    rate_limiter.until_ready().await;
+    repository: Arc<dyn Repository>,
+    mut cancelled: watch::Receiver<bool>,
+) -> Result<Option<SendEmailOutput>, SdkError<SendEmailError, Response>> {
+    let ready = wait_for_throttle(
+        &throttle,
+        &group,
+        request_uuid,
+        &recipients,
+        &repository,
+        &mut cancelled,
+        false,
+    )
+    .await;
+
+    if !ready {
+        return Ok(None);
+    }
 
     let mut result = send_email_op.clone().send().await;
     let mut attempt = 1;
@@ -87,10 +214,26 @@ async fn send_with_rate_limiter(
 
         thread::sleep(time::Duration::from_secs(RETRY_ATTEMPTS_INTERVAL.into()));
 
-        rate_limiter.until_ready().await;
+        throttle.until_ready(&group, &recipients).await;
         result = send_email_op.clone().send().await;
     }
 
+    let (state, ses_message_id, ses_error) = match &result {
+        Ok(output) => (
+            RecipientState::Sent,
+            output.message_id().map(|id| id.to_owned()),
+            None,
+        ),
+        Err(ses_err) => (RecipientState::Failed, None, Some(ses_err.to_string())),
+    };
+
+    if let Err(e) = repository
+        .set_recipients_state(request_uuid, &recipients, state, ses_message_id, ses_error)
+        .await
+    {
+        error!("failed to store recipients state: {}", e)
+    }
+
     if let Err(ses_err) = result {
         let sending_err_event =
             EmailSendingErrorEvent::new(ses_err.to_string(), request_uuid, recipients);
@@ -102,25 +245,248 @@ async fn send_with_rate_limiter(
         return Err(ses_err);
     }
 
-    result
+    result.map(Some)
+}
+
+/// sends a SendBulkEmail operation with a entry per recipient, in the same order as `recipients`,
+/// storing the state of each recipient from the status of its entry
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument]
+async fn send_bulk_with_rate_limiter(
+    throttle: Arc<SendThrottle>,
+    group: ThrottleGroup,
+    send_bulk_email_op: SendBulkEmailFluentBuilder,
+    request_uuid: uuid::Uuid,
+    recipients: Vec<String>,
+    server: Arc<queue::server::Server>,
+    repository: Arc<dyn Repository>,
+    mut cancelled: watch::Receiver<bool>,
+) -> Result<Option<SendBulkEmailOutput>, SdkError<SendBulkEmailError, Response>> {
+    let ready = wait_for_throttle(
+        &throttle,
+        &group,
+        request_uuid,
+        &recipients,
+        &repository,
+        &mut cancelled,
+        true,
+    )
+    .await;
+
+    if !ready {
+        return Ok(None);
+    }
+
+    let mut result = send_bulk_email_op.clone().send().await;
+    let mut attempt = 1;
+
+    while attempt < MAX_EMAIL_RETRY_ATTEMPT && result.is_err() {
+        attempt += 1;
+
+        thread::sleep(time::Duration::from_secs(RETRY_ATTEMPTS_INTERVAL.into()));
+
+        throttle.until_bulk_ready(&group, &recipients).await;
+        result = send_bulk_email_op.clone().send().await;
+    }
+
+    let output = match result {
+        Ok(output) => output,
+        Err(ses_err) => {
+            if let Err(e) = repository
+                .set_recipients_state(
+                    request_uuid,
+                    &recipients,
+                    RecipientState::Failed,
+                    None,
+                    Some(ses_err.to_string()),
+                )
+                .await
+            {
+                error!("failed to store recipients state: {}", e)
+            }
+
+            let sending_err_event =
+                EmailSendingErrorEvent::new(ses_err.to_string(), request_uuid, recipients);
+
+            if let Err(publishing_err) = server.publish_as_json(sending_err_event).await {
+                error!("failed to publish SES error to RMQ: {}", publishing_err)
+            }
+
+            return Err(ses_err);
+        }
+    };
+
+    // failed recipients grouped by error, so a error event is published per distinct error instead of per recipient
+    let mut failed_recipients: HashMap<String, Vec<String>> = HashMap::new();
+    let entry_results = output.bulk_email_entry_results().unwrap_or_default();
+
+    for (i, recipient) in recipients.iter().enumerate() {
+        let entry_result = entry_results.get(i);
+
+        let (state, ses_message_id, ses_error) = match entry_result.and_then(|r| r.status()) {
+            Some(BulkEmailStatus::Success) => (
+                RecipientState::Sent,
+                entry_result
+                    .and_then(|r| r.message_id())
+                    .map(|id| id.to_owned()),
+                None,
+            ),
+            status => {
+                let ses_error = format!(
+                    "{}: {}",
+                    status.map(|s| s.as_str()).unwrap_or("MissingEntryResult"),
+                    entry_result.and_then(|r| r.error()).unwrap_or_default()
+                );
+
+                failed_recipients
+                    .entry(ses_error.clone())
+                    .or_default()
+                    .push(recipient.to_owned());
+
+                (RecipientState::Failed, None, Some(ses_error))
+            }
+        };
+
+        if let Err(e) = repository
+            .set_recipients_state(
+                request_uuid,
+                &[recipient.to_owned()],
+                state,
+                ses_message_id,
+                ses_error,
+            )
+            .await
+        {
+            error!("failed to store recipients state: {}", e)
+        }
+    }
+
+    for (ses_error, failed) in failed_recipients {
+        let sending_err_event = EmailSendingErrorEvent::new(ses_error, request_uuid, failed);
+
+        if let Err(publishing_err) = server.publish_as_json(sending_err_event).await {
+            error!("failed to publish SES error to RMQ: {}", publishing_err)
+        }
+    }
+
+    Ok(Some(output))
 }
 
 impl Mailer {
-    pub async fn new(cfg: &config::AppConfig, server: Arc<server::Server>) -> Mailer {
-        let aws_cfg = aws_config::from_env()
-            .region(Region::new(cfg.aws_region.to_owned()))
-            .load()
-            .await;
+    pub async fn new(
+        cfg: &config::AppConfig,
+        server: Arc<server::Server>,
+        repository: Arc<dyn Repository>,
+    ) -> Mailer {
+        let rate_limiter = new_rate_limiter(cfg)
+            .unwrap_or_else(|e| panic!("[MAILER] failed to create rate limiter: {}", e));
 
-        let time_limit = NonZeroU32::new(cfg.aws_ses_max_emails_per_second).unwrap();
-        let rate_limiter = governor::RateLimiter::direct(Quota::per_second(time_limit));
+        let aws_client = new_ses_client(cfg).await;
 
         Mailer {
             server,
-            rate_limiter: Arc::new(rate_limiter),
-            aws_client: Client::new(&aws_cfg),
+            repository,
+            throttle: SendThrottle::start(cfg, rate_limiter.clone())
+                .unwrap_or_else(|e| panic!("[MAILER] failed to create throttle: {}", e)),
+            rate_limiter,
+            templates: Arc::new(SesTemplates::new(aws_client.clone())),
+            aws_client,
             default_sender: cfg.app_default_email_sender.to_owned(),
             aws_ses_tracking_config_set: cfg.aws_ses_tracking_config_set.to_owned(),
+            bulk_templates: cfg.aws_ses_bulk_templates,
+            in_progress: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// cancels a scheduled request or stops sending a request in progress, in which case this waits for the
+    /// chunks already fired to finish, so the reply and the `cancelled` event contain every sent recipient
+    ///
+    /// requests that are still waiting in the queue are unknown to the service and cannot be cancelled
+    pub async fn cancel_request(&self, uuid: Uuid) -> Result<CancelEmailRequestOut, String> {
+        let sent_recipients = if self.repository.cancel_scheduled_request(uuid).await? {
+            vec![]
+        } else {
+            let cancel_signal = self.in_progress.lock().unwrap().get(&uuid).cloned();
+
+            let Some(cancel_signal) = cancel_signal else {
+                return Ok(CancelEmailRequestOut {
+                    cancelled: false,
+                    sent_recipients: vec![],
+                });
+            };
+
+            cancel_signal.send_replace(true);
+            cancel_signal.closed().await;
+
+            self.repository
+                .set_request_state(uuid, RequestState::Cancelled)
+                .await?;
+
+            self.repository
+                .get_request_status(uuid)
+                .await?
+                .map(|status| {
+                    status
+                        .recipients
+                        .into_iter()
+                        .filter(|r| r.state.was_sent())
+                        .map(|r| r.email)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        println!(
+            "[MAILER] cancelled request {}, {} recipients were already sent",
+            uuid,
+            sent_recipients.len()
+        );
+
+        self.server
+            .publish_as_json(EmailRequestCancelledEvent::new(
+                uuid,
+                sent_recipients.clone(),
+            ))
+            .await?;
+
+        Ok(CancelEmailRequestOut {
+            cancelled: true,
+            sent_recipients,
+        })
+    }
+
+    /// the addresses of the recipients in the suppression list, if the suppression list cannot be read
+    /// the emails are sent to every recipient
+    pub async fn get_suppressed_recipients(&self, to: &[input::EmailRecipient]) -> Vec<String> {
+        let emails: Vec<String> = to.iter().map(|r| r.email.clone()).collect();
+
+        self.repository
+            .filter_suppressed(&emails)
+            .await
+            .unwrap_or_else(|e| {
+                error!("failed to check suppressed recipients: {}", e);
+                vec![]
+            })
+    }
+
+    /// sets the state of the suppressed recipients of a stored request and publishes a event with them
+    pub async fn skip_suppressed_recipients(&self, uuid: Uuid, suppressed: Vec<String>) {
+        if suppressed.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self
+            .repository
+            .set_recipients_state(uuid, &suppressed, RecipientState::Suppressed, None, None)
+            .await
+        {
+            error!("failed to store recipients state: {}", e)
+        }
+
+        let suppressed_event = EmailSuppressedEvent::new(uuid, suppressed);
+
+        if let Err(e) = self.server.publish_as_json(suppressed_event).await {
+            error!("failed to publish suppressed recipients event: {}", e)
         }
     }
 
@@ -128,20 +494,74 @@ impl Mailer {
         Content::builder().data(input).charset("UTF-8").build()
     }
 
+    /// content rendered by SES from a stored template, `template_data` is a JSON object with the replacements
+    fn to_template_content(&self, template_name: &str, template_data: String) -> EmailContent {
+        EmailContent::builder()
+            .template(
+                Template::builder()
+                    .template_name(template_name)
+                    .template_data(template_data)
+                    .build(),
+            )
+            .build()
+    }
+
     /// Sends the emails for all the recipients in parallel, passing uuid to the email tags.
     ///
     /// Each recipient with non empty replacements have the `body_html` {{}} tags
     /// replaced by the recipients replacements. Emails are send individually for
     /// every recipient with replacements or for every recipient if `track_events` is true.
     ///
-    /// this future resolves once all the emails have been sent
+    /// suppressed recipients must be removed by the caller, see `get_suppressed_recipients`
+    ///
+    /// this future resolves once all the emails have been sent or the request is cancelled
     #[tracing::instrument(skip(self))]
-    pub async fn send_emails(&self, options: SendEmailOptions) -> Result<(), String> {
+    pub async fn send_emails(
+        &self,
+        options: SendEmailOptions,
+    ) -> Result<SendEmailsOutcome, String> {
+        if options.to.is_empty() {
+            return Ok(SendEmailsOutcome::Finished);
+        }
+
+        let uuid = options.uuid;
+        let (cancel_signal, cancelled) = watch::channel(false);
+        let cancel_signal = Arc::new(cancel_signal);
+
+        self.in_progress
+            .lock()
+            .unwrap()
+            .insert(uuid, cancel_signal.clone());
+
+        let outcome = self.send_emails_until_cancelled(options, cancelled).await;
+
+        let mut in_progress = self.in_progress.lock().unwrap();
+
+        // a resend of the same request may have replaced the entry meanwhile
+        if let Some(current) = in_progress.get(&uuid) {
+            if Arc::ptr_eq(current, &cancel_signal) {
+                in_progress.remove(&uuid);
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    async fn send_emails_until_cancelled(
+        &self,
+        options: SendEmailOptions,
+        cancelled: watch::Receiver<bool>,
+    ) -> SendEmailsOutcome {
         let html = options.body_html.unwrap_or("".to_owned());
         let text = options.body_text.unwrap_or("".to_owned());
-        let subject = self.to_utf8_content(options.subject);
+        let subject_text = options.subject;
+        let subject = self.to_utf8_content(subject_text.clone());
 
         let uuid_str = options.uuid.to_string();
+        let group = ThrottleGroup {
+            tenant: options.tenant.unwrap_or_default(),
+            priority: options.priority,
+        };
 
         let from = options.from.unwrap_or(self.default_sender.to_owned());
 
@@ -163,38 +583,148 @@ impl Mailer {
             .value(uuid_str.clone())
             .build();
 
-        if !recipients_with_replacements.is_empty() {
+        let mut bulk_template_name = options.template.clone();
+
+        // templates created for the request are deleted once it is sent, templates referenced by name are kept
+        let mut created_template_name = None;
+
+        if self.bulk_templates
+            && bulk_template_name.is_none()
+            && !recipients_with_replacements.is_empty()
+        {
+            // a template per call, so concurrent sends of the same request (eg: resends) do not delete the template of each other
+            let template_name = format!(
+                "mailer-{}-{}",
+                options.uuid.simple(),
+                &Uuid::new_v4().simple().to_string()[..8]
+            );
+
+            let template = EmailTemplate {
+                name: template_name.clone(),
+                subject: subject_text.clone(),
+                html: Some(html.clone()),
+                // a empty text part would be sent to the recipients as a empty text/plain alternative
+                text: Some(text.clone()).filter(|text| !text.is_empty()),
+            };
+
+            match put_template(&self.templates, &template).await {
+                Ok(()) => {
+                    bulk_template_name = Some(template_name.clone());
+                    created_template_name = Some(template_name);
+                }
+                Err(e) => error!(
+                    "failed to create SES template, sending a email per recipient: {}",
+                    e
+                ),
+            }
+        }
+
+        let mut send_bulk_email_tasks = JoinSet::new();
+
+        let bulk_template_name = bulk_template_name.filter(|_| self.bulk_templates);
+
+        if let Some(template_name) = &bulk_template_name {
+            let default_content = BulkEmailContent::builder()
+                .template(
+                    Template::builder()
+                        .template_name(template_name)
+                        .template_data("{}")
+                        .build(),
+                )
+                .build();
+
+            for recipient_chunk in
+                recipients_with_replacements.chunks(MAX_ENTRIES_PER_SEND_BULK_EMAIL_OP)
+            {
+                let chunk_emails: Vec<String> =
+                    recipient_chunk.iter().map(|r| r.email.to_owned()).collect();
+
+                let entries = recipient_chunk
+                    .iter()
+                    .map(|recipient| {
+                        let template_data = serde_json::to_string(&recipient.replacements)
+                            .unwrap_or("{}".to_owned());
+
+                        BulkEmailEntry::builder()
+                            .destination(
+                                Destination::builder()
+                                    .to_addresses(recipient.email.clone())
+                                    .build(),
+                            )
+                            .replacement_email_content(
+                                ReplacementEmailContent::builder()
+                                    .replacement_template(
+                                        ReplacementTemplate::builder()
+                                            .replacement_template_data(template_data)
+                                            .build(),
+                                    )
+                                    .build(),
+                            )
+                            .build()
+                    })
+                    .collect();
+
+                send_bulk_email_tasks.spawn(
+                    send_bulk_with_rate_limiter(
+                        self.throttle.clone(),
+                        group.clone(),
+                        self.aws_client
+                            .send_bulk_email()
+                            .from_email_address(from.clone())
+                            .default_content(default_content.clone())
+                            .set_bulk_email_entries(Some(entries))
+                            .default_email_tags(email_id_tag.clone())
+                            .set_reply_to_addresses(options.reply_to_addresses.clone())
+                            .set_configuration_set_name(config_set.clone()),
+                        options.uuid,
+                        chunk_emails,
+                        self.server.clone(),
+                        self.repository.clone(),
+                        cancelled.clone(),
+                    )
+                    .instrument(tracing::Span::current()),
+                );
+            }
+        } else if !recipients_with_replacements.is_empty() {
             let mut reg = Handlebars::new();
 
             let template_registered = reg.register_template_string(&uuid_str, &html).is_ok();
 
             for recipient in recipients_with_replacements {
-                let recipient_html = if template_registered {
-                    reg.render(&uuid_str, &recipient.replacements)
-                        .unwrap_or(html.clone())
+                let email_content = if let Some(template_name) = &options.template {
+                    let template_data =
+                        serde_json::to_string(&recipient.replacements).unwrap_or("{}".to_owned());
+
+                    self.to_template_content(template_name, template_data)
                 } else {
-                    html.clone()
+                    let recipient_html = if template_registered {
+                        reg.render(&uuid_str, &recipient.replacements)
+                            .unwrap_or(html.clone())
+                    } else {
+                        html.clone()
+                    };
+
+                    let body = Body::builder()
+                        .html(self.to_utf8_content(recipient_html))
+                        .text(self.to_utf8_content(text.clone()))
+                        .build();
+
+                    let msg = Message::builder()
+                        .subject(subject.clone())
+                        .body(body)
+                        .build();
+
+                    EmailContent::builder().simple(msg).build()
                 };
 
-                let body = Body::builder()
-                    .html(self.to_utf8_content(recipient_html))
-                    .text(self.to_utf8_content(text.clone()))
-                    .build();
-
-                let msg = Message::builder()
-                    .subject(subject.clone())
-                    .body(body)
-                    .build();
-
-                let email_content = EmailContent::builder().simple(msg).build();
-
                 let dest = Destination::builder()
                     .to_addresses(recipient.email.clone())
                     .build();
 
                 send_email_tasks.spawn(
                     send_with_rate_limiter(
-                        self.rate_limiter.clone(),
+                        self.throttle.clone(),
+                        group.clone(),
                         self.aws_client
                             .send_email()
                             .from_email_address(from.clone())
@@ -203,9 +733,11 @@ impl Mailer {
                             .set_reply_to_addresses(options.reply_to_addresses.clone())
                             .set_configuration_set_name(config_set.clone())
                             .content(email_content.clone()),
-                        options.uuid.clone(),
+                        options.uuid,
                         vec![recipient.email.clone()],
                         self.server.clone(),
+                        self.repository.clone(),
+                        cancelled.clone(),
                     )
                     .instrument(tracing::Span::current()),
                 );
@@ -226,17 +758,21 @@ impl Mailer {
                     .map(|e| e.email.to_owned())
                     .collect();
 
-                let body = Body::builder()
-                    .html(self.to_utf8_content(html.clone()))
-                    .text(self.to_utf8_content(text.clone()))
-                    .build();
+                let email_content = if let Some(template_name) = &options.template {
+                    self.to_template_content(template_name, "{}".to_owned())
+                } else {
+                    let body = Body::builder()
+                        .html(self.to_utf8_content(html.clone()))
+                        .text(self.to_utf8_content(text.clone()))
+                        .build();
 
-                let msg = Message::builder()
-                    .subject(subject.clone())
-                    .body(body)
-                    .build();
+                    let msg = Message::builder()
+                        .subject(subject.clone())
+                        .body(body)
+                        .build();
 
-                let email_content = EmailContent::builder().simple(msg).build();
+                    EmailContent::builder().simple(msg).build()
+                };
 
                 let dest = Destination::builder()
                     .set_to_addresses(Some(chunk_emails.clone()))
@@ -244,7 +780,8 @@ impl Mailer {
 
                 send_email_tasks.spawn(
                     send_with_rate_limiter(
-                        self.rate_limiter.clone(),
+                        self.throttle.clone(),
+                        group.clone(),
                         self.aws_client
                             .send_email()
                             .from_email_address(from.clone())
@@ -253,17 +790,31 @@ impl Mailer {
                             .set_configuration_set_name(config_set.clone())
                             .set_reply_to_addresses(options.reply_to_addresses.clone())
                             .content(email_content.clone()),
-                        options.uuid.clone(),
+                        options.uuid,
                         chunk_emails.clone(),
                         self.server.clone(),
+                        self.repository.clone(),
+                        cancelled.clone(),
                     )
                     .instrument(tracing::Span::current()),
                 );
             }
         }
 
+        #[allow(clippy::redundant_pattern_matching)]
         while let Some(_) = send_email_tasks.join_next().await {}
+        while send_bulk_email_tasks.join_next().await.is_some() {}
+
+        if let Some(template_name) = created_template_name {
+            if let Err(e) = self.templates.delete(&template_name).await {
+                error!("failed to delete SES template {}: {}", template_name, e)
+            }
+        }
 
-        Ok(())
+        if *cancelled.borrow() {
+            SendEmailsOutcome::Cancelled
+        } else {
+            SendEmailsOutcome::Finished
+        }
     }
 }
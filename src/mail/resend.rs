@@ -0,0 +1,185 @@
+//! Resending of emails to recipients that bounced transiently (eg: mailbox full), such bounces are not retried by SES
+//!
+//! delivery delays are not resent since SES keeps retrying delayed deliveries by itself, if SES gives up
+//! the delay becomes a transient bounce and the recipient is resent by this policy.
+//!
+//! see: https://docs.aws.amazon.com/ses/latest/dg/notification-contents.html#bounce-types
+
+use crate::{
+    config,
+    controller::dto::{
+        events::{Email, EmailEvent, EmailResendGaveUpEvent},
+        input::ResendEmailIn,
+    },
+    queue::server::Server,
+    storage::repository::Repository,
+};
+use chrono::Utc;
+use std::{sync::Arc, time::Duration};
+use tokio::time;
+use tracing::error;
+use uuid::Uuid;
+
+/// interval between checks for due resends
+static RESEND_CHECK_INTERVAL_SECS: u64 = 30;
+
+/// see: https://docs.aws.amazon.com/ses/latest/dg/notification-contents.html#bounce-types
+static TRANSIENT_BOUNCE_TYPE: &str = "Transient";
+
+#[derive(Debug)]
+pub struct ResendPolicy {
+    server: Arc<Server>,
+    repository: Arc<dyn Repository>,
+
+    /// delay before each resend, the last delay is used for attempts beyond the list length
+    delays: Vec<Duration>,
+
+    max_attempts: u32,
+}
+
+impl ResendPolicy {
+    /// returns None if resending is disabled, by having no delays or a limit of 0 attempts
+    pub fn new(
+        cfg: &config::AppConfig,
+        server: Arc<Server>,
+        repository: Arc<dyn Repository>,
+    ) -> Option<ResendPolicy> {
+        if cfg.app_resend_delays_secs.is_empty() || cfg.app_resend_max_attempts == 0 {
+            return None;
+        }
+
+        Some(ResendPolicy {
+            server,
+            repository,
+            delays: cfg
+                .app_resend_delays_secs
+                .iter()
+                .map(|secs| Duration::from_secs(*secs))
+                .collect(),
+            max_attempts: cfg.app_resend_max_attempts,
+        })
+    }
+
+    fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let index = (attempt as usize)
+            .saturating_sub(1)
+            .min(self.delays.len() - 1);
+        self.delays[index]
+    }
+
+    /// schedules resends for the recipients of transient bounces, giving up on recipients that reached the attempts limit
+    pub async fn handle_event(&self, email_event: &EmailEvent) {
+        let Email::bounce(bounce) = &email_event.event else {
+            return;
+        };
+
+        if bounce.bounce_type != TRANSIENT_BOUNCE_TYPE {
+            return;
+        }
+
+        let Ok(request_uuid) = email_event.request_uuid.parse::<Uuid>() else {
+            return;
+        };
+
+        for recipient in &bounce.bounced_recipients {
+            let email = &recipient.email_address;
+            let last_bounce = format!("{}: {}", bounce.bounce_sub_type, recipient.diagnostic_code);
+
+            if let Err(e) = self.schedule(request_uuid, email, last_bounce).await {
+                error!("failed to schedule resend: {}", e)
+            }
+        }
+    }
+
+    async fn schedule(
+        &self,
+        request_uuid: Uuid,
+        email: &str,
+        last_bounce: String,
+    ) -> Result<(), String> {
+        let attempts = self
+            .repository
+            .get_resend_attempts(request_uuid, email)
+            .await?;
+
+        if attempts >= self.max_attempts {
+            println!(
+                "[RESEND] giving up on {} of request {} after {} resends",
+                email, request_uuid, attempts
+            );
+
+            let gave_up_event =
+                EmailResendGaveUpEvent::new(request_uuid, email.to_owned(), attempts, last_bounce);
+
+            self.server.publish_as_json(gave_up_event).await?;
+
+            return Ok(());
+        }
+
+        let attempt = attempts + 1;
+        let delay = chrono::Duration::from_std(self.delay_for_attempt(attempt))
+            .map_err(|e| e.to_string())?;
+
+        self.repository
+            .schedule_resend(
+                request_uuid,
+                email,
+                attempt,
+                Utc::now() + delay,
+                Some(last_bounce),
+            )
+            .await
+    }
+
+    /// periodically enqueues the due resends to the mailer queue as `resendEmail` deliveries, so they go through
+    /// the same checks as requests (cancellation, expiration, sender identities, tenant quota and warm-up)
+    pub async fn run(&self) {
+        let mut interval = time::interval(Duration::from_secs(RESEND_CHECK_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            let resends = match self.repository.take_due_resends(Utc::now()).await {
+                Ok(resends) => resends,
+                Err(e) => {
+                    error!("failed to get due resends: {}", e);
+                    continue;
+                }
+            };
+
+            for resend in resends {
+                let resend_email_in = ResendEmailIn {
+                    uuid: resend.request_uuid,
+                    email: resend.email,
+                    attempt: resend.attempt,
+                };
+
+                if let Err(e) = self
+                    .server
+                    .enqueue_as_json("resendEmail", None, resend_email_in.clone())
+                    .await
+                {
+                    error!(
+                        "failed to enqueue resend of request {}: {}",
+                        resend_email_in.uuid, e
+                    );
+
+                    // retried on the next check, keeping the attempt
+                    if let Err(e) = self
+                        .repository
+                        .schedule_resend(
+                            resend_email_in.uuid,
+                            &resend_email_in.email,
+                            resend_email_in.attempt,
+                            Utc::now(),
+                            None,
+                        )
+                        .await
+                    {
+                        error!("failed to reschedule resend: {}", e)
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Dispatch of requests with a `sendAt` in the future, stored by `Router::send_email` until they are due
+
+use crate::{queue::server::Server, storage::repository::Repository};
+use chrono::Utc;
+use std::sync::Arc;
+use tokio::time;
+use tracing::error;
+
+/// interval between checks for due scheduled requests
+static SCHEDULER_INTERVAL_SECS: u64 = 5;
+
+/// periodically enqueues the due scheduled requests to the mailer queue, so they are processed as
+/// any other request, requests are marked as enqueued only once enqueued, so a request that cannot be
+/// enqueued (or whose enqueue is interrupted by a restart) is retried on the next check
+pub async fn run_scheduler(server: Arc<Server>, repository: Arc<dyn Repository>) {
+    let mut interval = time::interval(time::Duration::from_secs(SCHEDULER_INTERVAL_SECS));
+
+    loop {
+        interval.tick().await;
+
+        let now = Utc::now();
+
+        let due_requests = match repository.get_due_scheduled_requests(now).await {
+            Ok(due_requests) => due_requests,
+            Err(e) => {
+                error!("failed to get due scheduled requests: {}", e);
+                continue;
+            }
+        };
+
+        for (uuid, mut request) in due_requests {
+            request.uuid = Some(uuid);
+
+            // the tenant is stored from the app id of the original delivery, which is the only tenant source
+            let tenant = request.tenant.clone();
+
+            if let Err(e) = server
+                .enqueue_as_json("sendEmail", tenant.as_deref(), request)
+                .await
+            {
+                error!("failed to enqueue scheduled request {}: {}", uuid, e);
+                continue;
+            }
+
+            println!("[SCHEDULER] enqueued scheduled request {}", uuid);
+
+            if let Err(e) = repository.mark_scheduled_request_enqueued(uuid, now).await {
+                error!(
+                    "failed to mark scheduled request {} as enqueued: {}",
+                    uuid, e
+                )
+            }
+        }
+    }
+}
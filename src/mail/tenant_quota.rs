@@ -0,0 +1,142 @@
+//! Daily quotas of emails per tenant, so a producer cannot use the whole SES account quota by itself
+
+use crate::{
+    config, controller::dto::events::EmailRequestOverQuotaEvent, storage::repository::Repository,
+};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct TenantQuotas {
+    repository: Arc<dyn Repository>,
+    daily_quotas: HashMap<String, u64>,
+    default_daily_quota: Option<u64>,
+}
+
+impl TenantQuotas {
+    pub fn new(
+        cfg: &config::AppConfig,
+        repository: Arc<dyn Repository>,
+    ) -> Result<TenantQuotas, String> {
+        Ok(TenantQuotas {
+            repository,
+            daily_quotas: config::parse_limits(cfg.app_tenant_max_emails_per_day.as_ref())
+                .map_err(|e| format!("invalid tenant daily quotas: {}", e))?,
+            default_daily_quota: cfg.app_tenant_default_max_emails_per_day,
+        })
+    }
+
+    /// reserves the emails of the request on the tenant quota, returning the event to publish if they exceed it,
+    /// emails are counted when the request is accepted, so emails that fail or are cancelled still count
+    pub async fn reserve(
+        &self,
+        tenant: &str,
+        request_uuid: Uuid,
+        emails: u64,
+    ) -> Result<Option<EmailRequestOverQuotaEvent>, String> {
+        let Some(daily_quota) = self
+            .daily_quotas
+            .get(tenant)
+            .copied()
+            .or(self.default_daily_quota)
+        else {
+            return Ok(None);
+        };
+
+        let reservation = self
+            .repository
+            .reserve_tenant_quota(tenant, request_uuid, emails, daily_quota)
+            .await?;
+
+        if reservation.reserved {
+            return Ok(None);
+        }
+
+        Ok(Some(EmailRequestOverQuotaEvent::new(
+            request_uuid,
+            tenant.to_owned(),
+            daily_quota,
+            reservation.used,
+            emails,
+        )))
+    }
+
+    /// releases the emails reserved for the request, so a request deferred to another day is only counted
+    /// when it is sent, releasing a request of a tenant without quota does nothing
+    pub async fn release(&self, tenant: &str, request_uuid: Uuid) -> Result<(), String> {
+        self.repository
+            .release_tenant_quota(tenant, request_uuid)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::sqlite::SqliteRepository;
+
+    fn tenant_quotas() -> TenantQuotas {
+        let cfg: config::AppConfig = envy::from_iter([(
+            "APP_TENANT_MAX_EMAILS_PER_DAY".to_owned(),
+            "acme=5".to_owned(),
+        )])
+        .unwrap();
+
+        TenantQuotas::new(&cfg, Arc::new(SqliteRepository::in_memory())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn request_over_the_quota_gets_the_over_quota_event() {
+        let tenant_quotas = tenant_quotas();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+
+        assert!(tenant_quotas
+            .reserve("acme", first, 3)
+            .await
+            .unwrap()
+            .is_none());
+
+        let event = tenant_quotas
+            .reserve("acme", second, 3)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(event.request_uuid, second);
+        assert_eq!(event.tenant, "acme");
+        assert_eq!(event.daily_quota, 5);
+        assert_eq!(event.used, 3);
+        assert_eq!(event.requested, 3);
+    }
+
+    #[tokio::test]
+    async fn tenant_without_quota_is_not_limited() {
+        let tenant_quotas = tenant_quotas();
+
+        assert!(tenant_quotas
+            .reserve("other", Uuid::new_v4(), 1_000)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn released_emails_do_not_count_on_the_quota() {
+        let tenant_quotas = tenant_quotas();
+        let (deferred, other) = (Uuid::new_v4(), Uuid::new_v4());
+
+        assert!(tenant_quotas
+            .reserve("acme", deferred, 3)
+            .await
+            .unwrap()
+            .is_none());
+
+        tenant_quotas.release("acme", deferred).await.unwrap();
+
+        assert!(tenant_quotas
+            .reserve("acme", other, 5)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,295 @@
+//! Throttling of sendEmail operations: per tenant and per recipient domain rate limits, followed by the account
+//! rate limit, whose permits are handed to the operations of higher priority first and to the waiting tenants
+//! of a priority in turns, so a tenant sending a large request does not starve the others
+
+use crate::{
+    config,
+    controller::dto::input::EmailPriority,
+    mail::rate_limiter::{KeyedRateLimiter, SendRateLimiter},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{oneshot, Notify};
+
+/// operations throttled together, a sendEmail operation waits in the lane of its priority for the turn of its tenant
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ThrottleGroup {
+    pub tenant: String,
+    pub priority: EmailPriority,
+}
+
+/// operations of a priority waiting for a account permit, grouped by tenant
+#[derive(Debug, Default)]
+struct WaitingOperations {
+    /// tenants with waiting operations, in the order they get their next permit
+    turns: VecDeque<String>,
+
+    waiters: HashMap<String, VecDeque<oneshot::Sender<()>>>,
+}
+
+impl WaitingOperations {
+    fn push(&mut self, tenant: &str, waiter: oneshot::Sender<()>) {
+        let tenant_waiters = self.waiters.entry(tenant.to_owned()).or_default();
+
+        if tenant_waiters.is_empty() {
+            self.turns.push_back(tenant.to_owned());
+        }
+
+        tenant_waiters.push_back(waiter);
+    }
+
+    /// the oldest waiter of the tenant whose turn it is, the tenant goes to the end of the turns if it has more waiters
+    fn pop(&mut self) -> Option<oneshot::Sender<()>> {
+        let tenant = self.turns.pop_front()?;
+        let tenant_waiters = self.waiters.get_mut(&tenant)?;
+        let waiter = tenant_waiters.pop_front();
+
+        if tenant_waiters.is_empty() {
+            self.waiters.remove(&tenant);
+        } else {
+            self.turns.push_back(tenant);
+        }
+
+        waiter
+    }
+
+    fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct SendThrottle {
+    rate_limiter: Arc<dyn SendRateLimiter>,
+    tenant_rate_limiter: KeyedRateLimiter,
+    domain_rate_limiter: KeyedRateLimiter,
+    /// waiting operations of each priority, from the highest to the lowest
+    lanes: Mutex<[WaitingOperations; 3]>,
+    waiter_added: Notify,
+
+    /// percentage of the account permits only handed to high priority operations
+    high_priority_reserved_percent: u64,
+}
+
+impl SendThrottle {
+    /// creates the throttle and starts handing out the account permits
+    pub fn start(
+        cfg: &config::AppConfig,
+        rate_limiter: Arc<dyn SendRateLimiter>,
+    ) -> Result<Arc<SendThrottle>, String> {
+        let throttle = Arc::new(SendThrottle {
+            rate_limiter,
+            tenant_rate_limiter: KeyedRateLimiter::new(
+                cfg.app_tenant_max_emails_per_second.as_ref(),
+                cfg.app_tenant_default_max_emails_per_second,
+            )
+            .map_err(|e| format!("invalid tenant rate limits: {}", e))?,
+            domain_rate_limiter: KeyedRateLimiter::new(
+                cfg.aws_ses_domain_max_emails_per_second.as_ref(),
+                cfg.aws_ses_domain_default_max_emails_per_second,
+            )
+            .map_err(|e| format!("invalid domain rate limits: {}", e))?,
+            lanes: Mutex::new(Default::default()),
+            waiter_added: Notify::new(),
+            high_priority_reserved_percent: cfg.app_high_priority_reserved_percent.min(100).into(),
+        });
+
+        tokio::spawn(throttle.clone().hand_out_permits());
+
+        Ok(throttle)
+    }
+
+    /// resolves once a sendEmail operation for the recipients can be made, a tenant and domain permit is taken
+    /// for each recipient since a operation with many recipients delivers a email to each of them
+    pub async fn until_ready(&self, group: &ThrottleGroup, recipients: &[String]) {
+        self.until_ready_with_permits(group, recipients, 1).await
+    }
+
+    /// resolves once a SendBulkEmail operation for the recipients can be made, SES counts each of its entries
+    /// as a message on the account rate, so a account permit is also taken for each recipient
+    pub async fn until_bulk_ready(&self, group: &ThrottleGroup, recipients: &[String]) {
+        self.until_ready_with_permits(group, recipients, recipients.len().max(1))
+            .await
+    }
+
+    async fn until_ready_with_permits(
+        &self,
+        group: &ThrottleGroup,
+        recipients: &[String],
+        account_permits: usize,
+    ) {
+        for recipient in recipients {
+            self.tenant_rate_limiter
+                .until_key_ready(&group.tenant)
+                .await;
+
+            if let Some((_, domain)) = recipient.rsplit_once('@') {
+                self.domain_rate_limiter
+                    .until_key_ready(&domain.trim().to_lowercase())
+                    .await;
+            }
+        }
+
+        // each permit is a turn of the tenant, so the permits of a bulk operation are interleaved with other tenants
+        let permits: Vec<_> = {
+            let mut lanes = self.lanes.lock().unwrap();
+
+            (0..account_permits)
+                .map(|_| {
+                    let (waiter, permit) = oneshot::channel();
+                    lanes[lane_index(group.priority)].push(&group.tenant, waiter);
+                    permit
+                })
+                .collect()
+        };
+        self.waiter_added.notify_one();
+
+        for permit in permits {
+            // the sender is only dropped without sending if the throttle stops, which only happens on shutdown
+            let _ = permit.await;
+        }
+    }
+
+    async fn hand_out_permits(self: Arc<Self>) {
+        let mut has_permit = false;
+        let mut permits_taken: u64 = 0;
+        let mut reserved = false;
+
+        loop {
+            if !has_permit {
+                self.wait_for_waiters().await;
+                self.rate_limiter.until_ready().await;
+
+                // reserved permits are spread evenly, eg: with 20% every fifth permit is reserved
+                reserved = (permits_taken + 1) * self.high_priority_reserved_percent / 100
+                    > permits_taken * self.high_priority_reserved_percent / 100;
+
+                permits_taken += 1;
+                has_permit = true;
+            }
+
+            let waiter = {
+                let mut lanes = self.lanes.lock().unwrap();
+
+                if reserved {
+                    lanes[lane_index(EmailPriority::High)].pop()
+                } else {
+                    lanes.iter_mut().find_map(|lane| lane.pop())
+                }
+            };
+
+            match waiter {
+                // waiters that gave up (eg: their request was cancelled) are skipped, keeping the permit for the next one
+                Some(waiter) => has_permit = waiter.send(()).is_err(),
+                // a reserved permit without high priority operations waiting is left unused
+                None if reserved => has_permit = false,
+                None => self.wait_for_waiters().await,
+            }
+        }
+    }
+
+    async fn wait_for_waiters(&self) {
+        while self
+            .lanes
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|lane| lane.is_empty())
+        {
+            self.waiter_added.notified().await;
+        }
+    }
+}
+
+fn lane_index(priority: EmailPriority) -> usize {
+    match priority {
+        EmailPriority::High => 0,
+        EmailPriority::Normal => 1,
+        EmailPriority::Low => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mail::rate_limiter::InMemoryRateLimiter;
+    use std::{num::NonZeroU32, time::Duration};
+    use tokio::{sync::mpsc, time::sleep};
+
+    fn group(tenant: &str) -> ThrottleGroup {
+        ThrottleGroup {
+            tenant: tenant.to_owned(),
+            priority: EmailPriority::Normal,
+        }
+    }
+
+    fn recipients(amount: usize) -> Vec<String> {
+        (0..amount)
+            .map(|i| format!("user{}@example.com", i))
+            .collect()
+    }
+
+    #[test]
+    fn waiting_tenants_take_turns() {
+        let mut waiting = WaitingOperations::default();
+        let mut permits = vec![];
+
+        for tenant in ["large", "large", "large", "small"] {
+            let (waiter, permit) = oneshot::channel();
+            waiting.push(tenant, waiter);
+            permits.push((tenant, permit));
+        }
+
+        let mut order = vec![];
+
+        while let Some(waiter) = waiting.pop() {
+            waiter.send(()).unwrap();
+
+            let index = permits
+                .iter_mut()
+                .position(|(_, permit)| permit.try_recv().is_ok())
+                .unwrap();
+
+            order.push(permits.remove(index).0);
+        }
+
+        assert_eq!(order, vec!["large", "small", "large", "large"]);
+        assert!(waiting.is_empty());
+    }
+
+    #[tokio::test]
+    async fn bulk_permits_are_interleaved_with_other_tenants() {
+        let cfg: config::AppConfig = envy::from_iter([(
+            "APP_HIGH_PRIORITY_RESERVED_PERCENT".to_owned(),
+            "0".to_owned(),
+        )])
+        .unwrap();
+
+        let rate = NonZeroU32::new(10).unwrap();
+        let throttle = SendThrottle::start(&cfg, Arc::new(InMemoryRateLimiter::new(rate))).unwrap();
+
+        // takes the burst of the rate limiter, so the next permits are handed every 100ms
+        for _ in 0..rate.get() {
+            throttle.until_ready(&group("burst"), &[]).await;
+        }
+
+        let (ready, mut ready_order) = mpsc::unbounded_channel();
+
+        for (tenant, recipients) in [("bulk", recipients(3)), ("single", recipients(1))] {
+            let (throttle, ready) = (throttle.clone(), ready.clone());
+
+            tokio::spawn(async move {
+                throttle.until_bulk_ready(&group(tenant), &recipients).await;
+                ready.send(tenant).unwrap();
+            });
+
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        // with a permit per call the bulk operation would take the first permit and be ready first
+        assert_eq!(ready_order.recv().await, Some("single"));
+        assert_eq!(ready_order.recv().await, Some("bulk"));
+    }
+}
@@ -0,0 +1,297 @@
+//! Warm-up of new sender identities (addresses or domains), whose volume must grow over days or weeks
+//! so mailbox providers build a reputation for them before receiving large amounts of emails
+//!
+//! see: https://docs.aws.amazon.com/ses/latest/dg/dedicated-ip-warming.html
+
+use crate::{
+    config,
+    controller::{
+        dto::{
+            events::{EmailRequestDeferredEvent, SenderWarmupProgressEvent},
+            output::SenderWarmupProgress,
+        },
+        validation::get_sender_address,
+    },
+    queue::server::Server,
+    storage::repository::Repository,
+};
+use chrono::{Days, NaiveDate, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+struct WarmupPlan {
+    start: NaiveDate,
+
+    /// emails the identity can send on each day of the plan
+    daily_caps: Vec<u64>,
+}
+
+impl WarmupPlan {
+    /// parses a `<start date>:<cap>/<cap>/...` plan, eg: `2026-10-01:50/100/500`
+    fn parse(plan: &str) -> Result<WarmupPlan, String> {
+        let (start, daily_caps) = plan
+            .split_once(':')
+            .ok_or(format!("expected <start date>:<daily caps>: {}", plan))?;
+
+        let start = start
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid start date: {}", start))?;
+
+        let daily_caps = daily_caps
+            .split('/')
+            .map(|cap| {
+                cap.trim()
+                    .parse()
+                    .map_err(|_| format!("invalid daily cap: {}", cap))
+            })
+            .collect::<Result<Vec<u64>, String>>()?;
+
+        Ok(WarmupPlan { start, daily_caps })
+    }
+
+    /// day of the plan on `today`, days before the start date are the first day
+    fn day(&self, today: NaiveDate) -> u32 {
+        (today - self.start).num_days().clamp(0, u32::MAX.into()) as u32
+    }
+
+    /// cap of the day, None after the last day of the plan
+    fn daily_cap(&self, day: u32) -> Option<u64> {
+        self.daily_caps.get(day as usize).copied()
+    }
+
+    /// first day after `today` whose cap fits `emails`, or the day after the plan ends, caps grow
+    /// over the plan so requests larger than the cap of today are sent once it is large enough
+    fn next_send_date(&self, today: NaiveDate, emails: u64) -> Option<NaiveDate> {
+        let mut date = today.checked_add_days(Days::new(1))?;
+
+        while self
+            .daily_cap(self.day(date))
+            .is_some_and(|cap| cap < emails)
+        {
+            date = date.checked_add_days(Days::new(1))?;
+        }
+
+        Some(date)
+    }
+}
+
+#[derive(Debug)]
+pub struct SenderWarmup {
+    server: Arc<Server>,
+    repository: Arc<dyn Repository>,
+    plans: HashMap<String, WarmupPlan>,
+    default_sender: String,
+
+    /// last day each identity reached its cap, so the progress event for it is published once per day
+    cap_reached_on: Mutex<HashMap<String, NaiveDate>>,
+}
+
+impl SenderWarmup {
+    pub fn new(
+        cfg: &config::AppConfig,
+        server: Arc<Server>,
+        repository: Arc<dyn Repository>,
+    ) -> Result<SenderWarmup, String> {
+        let mut plans = HashMap::new();
+
+        for entry in cfg.app_warmup_plans.iter().flatten() {
+            let (identity, plan) = entry.split_once('=').ok_or(format!(
+                "invalid warm-up plan, expected <identity>=<plan>: {}",
+                entry
+            ))?;
+
+            let plan = WarmupPlan::parse(plan)
+                .map_err(|e| format!("invalid warm-up plan for {}: {}", identity, e))?;
+
+            plans.insert(identity.trim().to_lowercase(), plan);
+        }
+
+        Ok(SenderWarmup {
+            server,
+            repository,
+            plans,
+            default_sender: cfg.app_default_email_sender.to_owned(),
+            cap_reached_on: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// the identity with a plan for the sender, a plan for the sender address takes precedence over its domain plan
+    fn get_plan(&self, sender: Option<&str>) -> Option<(String, &WarmupPlan)> {
+        let address = get_sender_address(sender.unwrap_or(&self.default_sender));
+        let domain = address
+            .rsplit_once('@')
+            .map(|(_, domain)| domain.to_owned());
+
+        [Some(address), domain]
+            .into_iter()
+            .flatten()
+            .find_map(|identity| {
+                let plan = self.plans.get(&identity)?;
+                Some((identity, plan))
+            })
+    }
+
+    /// reserves the emails of the request on the cap of today of its sender identity, returning the event to
+    /// publish if they exceed it, in which case the request must be sent on the first day it fits
+    pub async fn reserve(
+        &self,
+        sender: Option<&str>,
+        request_uuid: Uuid,
+        emails: u64,
+    ) -> Result<Option<EmailRequestDeferredEvent>, String> {
+        let Some((identity, plan)) = self.get_plan(sender) else {
+            return Ok(None);
+        };
+
+        let today = Utc::now().date_naive();
+        let day = plan.day(today);
+
+        let Some(daily_cap) = plan.daily_cap(day) else {
+            return Ok(None);
+        };
+
+        let reservation = self
+            .repository
+            .reserve_warmup_emails(&identity, request_uuid, emails, today, daily_cap)
+            .await?;
+
+        let progress = SenderWarmupProgress {
+            identity: identity.clone(),
+            day,
+            days: plan.daily_caps.len() as u32,
+            daily_cap: Some(daily_cap),
+            used: reservation.used,
+        };
+
+        if reservation.reserved {
+            if reservation.used == 0 {
+                self.publish_progress(SenderWarmupProgress {
+                    used: emails,
+                    ..progress
+                })
+                .await;
+            }
+
+            return Ok(None);
+        }
+
+        let cap_reached_today = self
+            .cap_reached_on
+            .lock()
+            .unwrap()
+            .insert(identity.clone(), today)
+            == Some(today);
+
+        if !cap_reached_today {
+            println!(
+                "[WARMUP] {} reached its cap of {} emails for day {}",
+                identity, daily_cap, day
+            );
+
+            self.publish_progress(progress).await;
+        }
+
+        let send_at = plan
+            .next_send_date(today, emails)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .ok_or("failed to get the day to send the request")?
+            .and_utc();
+
+        Ok(Some(EmailRequestDeferredEvent::new(
+            request_uuid,
+            identity,
+            daily_cap,
+            reservation.used,
+            emails,
+            send_at,
+        )))
+    }
+
+    /// progress of every configured plan on the current UTC day
+    pub async fn get_progress(&self) -> Result<Vec<SenderWarmupProgress>, String> {
+        let today = Utc::now().date_naive();
+        let mut progress = Vec::with_capacity(self.plans.len());
+
+        for (identity, plan) in &self.plans {
+            let day = plan.day(today);
+
+            progress.push(SenderWarmupProgress {
+                identity: identity.to_owned(),
+                day,
+                days: plan.daily_caps.len() as u32,
+                daily_cap: plan.daily_cap(day),
+                used: self.repository.get_warmup_usage(identity, today).await?,
+            });
+        }
+
+        progress.sort_by(|a, b| a.identity.cmp(&b.identity));
+
+        Ok(progress)
+    }
+
+    async fn publish_progress(&self, progress: SenderWarmupProgress) {
+        if let Err(e) = self
+            .server
+            .publish_as_json(SenderWarmupProgressEvent::new(progress))
+            .await
+        {
+            error!("failed to publish warm-up progress: {}", e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    #[test]
+    fn defers_to_the_next_day_when_the_request_fits_its_cap() {
+        let plan = WarmupPlan::parse("2026-10-01:50/100/500").unwrap();
+
+        assert_eq!(
+            plan.next_send_date(date("2026-10-01"), 80),
+            Some(date("2026-10-02"))
+        );
+    }
+
+    #[test]
+    fn defers_to_the_first_day_whose_cap_fits_the_request() {
+        let plan = WarmupPlan::parse("2026-10-01:50/100/500").unwrap();
+
+        assert_eq!(
+            plan.next_send_date(date("2026-10-01"), 300),
+            Some(date("2026-10-03"))
+        );
+    }
+
+    #[test]
+    fn defers_to_the_end_of_the_plan_when_no_cap_fits_the_request() {
+        let plan = WarmupPlan::parse("2026-10-01:50/100/500").unwrap();
+
+        assert_eq!(
+            plan.next_send_date(date("2026-10-01"), 10_000),
+            Some(date("2026-10-04"))
+        );
+    }
+
+    #[test]
+    fn days_before_the_start_use_the_first_cap() {
+        let plan = WarmupPlan::parse("2026-10-10:50/100").unwrap();
+
+        assert_eq!(plan.day(date("2026-10-01")), 0);
+        assert_eq!(
+            plan.next_send_date(date("2026-10-01"), 80),
+            Some(date("2026-10-11"))
+        );
+    }
+}
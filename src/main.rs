@@ -1,38 +1,82 @@
+use cli::{replay::ReplayOptions, suppressions::SuppressionsOptions};
 use config::AppConfig;
 use controller::router::Router;
 use lapin::message::Delivery;
-use mail::mailer::Mailer;
+use mail::{
+    mailer::Mailer, resend::ResendPolicy, tenant_quota::TenantQuotas, warmup::SenderWarmup,
+};
 use queue::server::Server;
+use ses::{
+    dedup::SnsMessageDeduplicator, handler::SesEventHandler, identities::SenderIdentities,
+    quota::SesQuotaMonitor,
+};
 use signal_hook::{
     consts::{SIGINT, SIGTERM},
     iterator::Signals,
 };
-use std::sync::Arc;
+use sqs::{client::SqsClient, poller::SqsPoller};
+use std::{sync::Arc, time::Duration};
+use storage::{repository::Repository, sqlite::SqliteRepository};
 use tokio::sync::mpsc;
 use trace::tracer;
 
+mod cli {
+    pub mod replay;
+    pub mod suppressions;
+}
 mod config;
 mod controller {
     pub mod routes {
         pub mod default;
         pub mod email;
+        pub mod status;
+        pub mod suppression;
+        pub mod template;
     }
     pub mod dto {
         pub mod events;
         pub mod input;
+        pub mod output;
         pub mod ses;
+        pub mod status;
+        pub mod suppression;
+        pub mod template;
     }
     pub mod router;
     pub mod validation;
 }
 mod mail {
     pub mod mailer;
+    pub mod rate_limiter;
+    pub mod resend;
+    pub mod scheduler;
+    pub mod tenant_quota;
+    pub mod throttle;
+    pub mod warmup;
 }
 mod queue {
     pub mod server;
 }
 mod http {
     pub mod server;
+    pub mod sns;
+}
+mod ses {
+    pub mod client;
+    pub mod dedup;
+    pub mod handler;
+    pub mod identities;
+    pub mod quota;
+    pub mod suppression_sync;
+    pub mod templates;
+}
+mod sqs {
+    pub mod client;
+    pub mod poller;
+}
+mod storage {
+    pub mod repository;
+    pub mod sqlite;
 }
 mod trace {
     pub mod tracer;
@@ -45,6 +89,12 @@ mod utils {
 async fn main() {
     let cfg = AppConfig::from_env().expect("failed to load application config");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if let Some(command) = args.first() {
+        return run_command(&cfg, command, &args[1..]).await;
+    }
+
     tracer::init(cfg.tracer_service_name.to_owned()).expect("failed to init tracer");
 
     let (sender, mut receiver) = mpsc::unbounded_channel::<Delivery>();
@@ -54,15 +104,119 @@ async fn main() {
     let http_server_ref = server.clone();
     let shutdown_server_ref = server.clone();
 
-    let mailer = Mailer::new(&cfg, server.clone()).await;
+    let repository: Arc<dyn Repository> = Arc::new(
+        SqliteRepository::new(&cfg.db_sqlite_path, cfg.db_pool_size)
+            .expect("failed to open database"),
+    );
+
+    let http_repository_ref = repository.clone();
+
+    tokio::spawn(storage::repository::run_retention_policy(
+        repository.clone(),
+        cfg.db_retention_days,
+    ));
+
+    tokio::spawn(mail::scheduler::run_scheduler(
+        server.clone(),
+        repository.clone(),
+    ));
+
+    let deduplicator = SnsMessageDeduplicator::new(
+        Duration::from_secs(cfg.aws_sns_dedup_ttl_secs),
+        cfg.aws_sns_dedup_persist.then(|| repository.clone()),
+    );
+
+    let resend_policy = ResendPolicy::new(&cfg, server.clone(), repository.clone()).map(Arc::new);
+
+    let ses_event_handler = Arc::new(SesEventHandler::new(
+        server.clone(),
+        repository.clone(),
+        deduplicator,
+        cfg.rmq_email_events_format,
+        cfg.rmq_email_events_per_recipient,
+        resend_policy.clone(),
+    ));
+
+    if let Some(queue_url) = &cfg.aws_sqs_ses_events_queue_url {
+        let sqs_client = SqsClient::new(&cfg).await;
+
+        let poller = SqsPoller::new(
+            sqs_client,
+            queue_url.to_owned(),
+            cfg.aws_sqs_wait_time_secs,
+            ses_event_handler.clone(),
+        );
+
+        tokio::spawn(async move { poller.start().await });
+    }
+
+    let mailer = Arc::new(Mailer::new(&cfg, server.clone(), repository.clone()).await);
+
+    if cfg.aws_ses_quota_refresh_secs > 0 {
+        let quota_monitor = SesQuotaMonitor::new(
+            &cfg,
+            mailer.aws_client.clone(),
+            mailer.rate_limiter.clone(),
+            server.clone(),
+        );
+
+        tokio::spawn(async move { quota_monitor.run().await });
+    }
+
+    let sender_identities = Arc::new(
+        SenderIdentities::new(&cfg, mailer.aws_client.clone())
+            .expect("failed to create sender identities"),
+    );
+
+    {
+        let sender_identities = sender_identities.clone();
+        tokio::spawn(async move { sender_identities.run().await });
+    }
+
+    if let Some(resend_policy) = resend_policy {
+        tokio::spawn(async move { resend_policy.run().await });
+    }
+
+    let http_mailer_ref = mailer.clone();
+    let templates = mailer.templates.clone();
+    let http_templates_ref = templates.clone();
+    let tenant_quotas =
+        TenantQuotas::new(&cfg, repository.clone()).expect("failed to create tenant quotas");
+
+    let warmup = Arc::new(
+        SenderWarmup::new(&cfg, server.clone(), repository.clone())
+            .expect("failed to create sender warm-up"),
+    );
+    let http_warmup_ref = warmup.clone();
 
-    let router = Arc::new(Router::new(server.clone(), mailer));
+    let router = Arc::new(Router::new(
+        server.clone(),
+        mailer,
+        repository,
+        tenant_quotas,
+        warmup,
+        sender_identities,
+        templates,
+    ));
 
     tokio::spawn(async move { server.clone().start().await });
-    tokio::spawn(async move { http::server::serve(&cfg, http_server_ref).await });
+    tokio::spawn(async move {
+        http::server::serve(
+            &cfg,
+            http_server_ref,
+            http_repository_ref,
+            http_mailer_ref,
+            http_warmup_ref,
+            http_templates_ref,
+            ses_event_handler,
+        )
+        .await
+    });
 
+    #[allow(clippy::needless_borrows_for_generic_args)]
     let mut signals = Signals::new(&[SIGINT, SIGTERM]).expect("failed to setup signals hook");
 
+    #[allow(clippy::never_loop)]
     tokio::spawn(async move {
         for sig in signals.forever() {
             println!("\n[APP] received signal: {}, shutting down", sig);
@@ -79,3 +233,26 @@ async fn main() {
         tokio::spawn(async move { router.handle_delivery(delivery).await });
     }
 }
+
+/// runs a CLI command instead of the service, exiting with a non zero code if the command fails
+async fn run_command(cfg: &AppConfig, command: &str, args: &[String]) {
+    let result = match command {
+        "replay-events" => match ReplayOptions::from_args(args) {
+            Ok(options) => cli::replay::run(cfg, options).await,
+            Err(e) => Err(format!("{}\n\n{}", e, cli::replay::USAGE)),
+        },
+        "ses-suppressions" => match SuppressionsOptions::from_args(args) {
+            Ok(options) => cli::suppressions::run(cfg, options).await,
+            Err(e) => Err(format!("{}\n\n{}", e, cli::suppressions::USAGE)),
+        },
+        _ => Err(format!(
+            "unknown command: {}, available commands: replay-events, ses-suppressions",
+            command
+        )),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    }
+}
@@ -9,7 +9,7 @@ use lapin::{
 };
 use serde::Serialize;
 use std::{thread, time};
-use tokio::sync::{mpsc::UnboundedSender, RwLock};
+use tokio::sync::{mpsc::UnboundedSender, watch, RwLock};
 use tokio_stream::StreamExt;
 
 use crate::{config, utils::errors};
@@ -17,6 +17,9 @@ use crate::{config, utils::errors};
 static ERR_EMPTY_CHANNEL: &str = "channel not set";
 static ERR_PUBLISH_CONFIRM: &str = "failed to confirm publishing";
 
+/// see: https://www.rabbitmq.com/publishers.html#message-properties
+static PERSISTENT_DELIVERY_MODE: u8 = 2;
+
 #[derive(Debug)]
 pub struct Options {
     pub uri: String,
@@ -31,6 +34,10 @@ pub struct Server {
     channel: RwLock<Option<Channel>>,
     connection: RwLock<Option<Connection>>,
     sender: UnboundedSender<Delivery>,
+
+    /// while true sendEmail deliveries are held unacked by the router until resumed, other deliveries
+    /// (eg: status and admin RPCs) are still handled
+    sending_paused: watch::Sender<bool>,
 }
 
 pub trait Routable {
@@ -38,6 +45,7 @@ pub trait Routable {
 }
 
 impl Server {
+    #[allow(clippy::redundant_field_names)]
     pub fn new(cfg: &config::AppConfig, sender: UnboundedSender<Delivery>) -> Server {
         let options = Options {
             uri: cfg.rmq_uri.to_owned(),
@@ -51,9 +59,30 @@ impl Server {
             options: options,
             channel: RwLock::new(None),
             connection: RwLock::new(None),
+            sending_paused: watch::channel(false).0,
         }
     }
 
+    /// pauses or resumes handling sendEmail deliveries, eg: when close to the SES daily sending quota
+    pub fn set_sending_paused(&self, paused: bool) {
+        if self.sending_paused.send_replace(paused) != paused {
+            println!(
+                "[RMQ] sendEmail deliveries {}",
+                if paused { "paused" } else { "resumed" }
+            );
+        }
+    }
+
+    /// resolves once sending is not paused
+    pub async fn until_sending_resumed(&self) {
+        // the sender lives as long as the server, so waiting cannot fail while it is borrowed
+        let _ = self
+            .sending_paused
+            .subscribe()
+            .wait_for(|paused| !paused)
+            .await;
+    }
+
     pub async fn start(&self) {
         loop {
             if let Err(err) = self.run().await {
@@ -81,7 +110,18 @@ impl Server {
         }
     }
 
-    async fn run(&self) -> Result<(), lapin::Error> {
+    /// connects to RMQ and declares the events exchange, without consuming the mailer queue,
+    /// so the server can publish events from processes that do not handle deliveries (eg: CLI commands)
+    pub async fn connect(&self) -> Result<(), lapin::Error> {
+        let (connection, channel) = self.open_channel().await?;
+
+        *self.connection.write().await = Some(connection);
+        *self.channel.write().await = Some(channel);
+
+        Ok(())
+    }
+
+    async fn open_channel(&self) -> Result<(Connection, Channel), lapin::Error> {
         let props = ConnectionProperties::default()
             .with_executor(tokio_executor_trait::Tokio::current())
             .with_reactor(tokio_reactor_trait::Tokio);
@@ -110,6 +150,12 @@ impl Server {
         );
         println!("[RMQ] events exchange declared");
 
+        Ok((connection, channel))
+    }
+
+    async fn run(&self) -> Result<(), lapin::Error> {
+        let (connection, channel) = self.open_channel().await?;
+
         errors::exit_on_err(
             channel
                 .queue_declare(
@@ -181,17 +227,92 @@ impl Server {
             .or(Err(ERR_PUBLISH_CONFIRM.to_owned()))
     }
 
+    /// publishes `payload` as JSON to the `reply_to` queue of a RPC delivery, using its correlation id
+    pub async fn reply_as_json<T>(
+        &self,
+        delivery: &Delivery,
+        payload: T,
+    ) -> Result<PublisherConfirm, String>
+    where
+        T: Serialize,
+    {
+        let reply_to = delivery
+            .properties
+            .reply_to()
+            .clone()
+            .ok_or("delivery has no reply_to property")?;
+
+        let json =
+            serde_json::to_string(&payload).or(Err("failed to serialize reply".to_owned()))?;
+
+        let mut properties =
+            BasicProperties::default().with_content_type("application/json".into());
+
+        if let Some(correlation_id) = delivery.properties.correlation_id() {
+            properties = properties.with_correlation_id(correlation_id.clone());
+        }
+
+        self.publish("", reply_to.as_str(), json.as_bytes(), properties)
+            .await
+    }
+
+    /// publishes `payload` as a persistent JSON message of type `kind` to the queue consumed by this service,
+    /// so it is processed exactly as if a producer sent it through RMQ, with `app_id` as its AMQP `app_id`
+    pub async fn enqueue_as_json<T>(
+        &self,
+        kind: &str,
+        app_id: Option<&str>,
+        payload: T,
+    ) -> Result<PublisherConfirm, String>
+    where
+        T: Serialize,
+    {
+        let json =
+            serde_json::to_string(&payload).or(Err("failed to serialize payload".to_owned()))?;
+
+        let mut properties = BasicProperties::default()
+            .with_content_type("application/json".into())
+            .with_type(kind.into())
+            .with_delivery_mode(PERSISTENT_DELIVERY_MODE);
+
+        if let Some(app_id) = app_id {
+            properties = properties.with_app_id(app_id.into());
+        }
+
+        self.publish("", &self.options.queue, json.as_bytes(), properties)
+            .await
+    }
+
     pub async fn publish_as_json<T>(&self, event: T) -> Result<PublisherConfirm, String>
+    where
+        T: Serialize + Routable,
+    {
+        self.publish_as_json_with_id(event, None).await
+    }
+
+    /// publishes the event with `message_id` as its AMQP `message_id`, so consumers can drop events published twice
+    pub async fn publish_as_json_with_id<T>(
+        &self,
+        event: T,
+        message_id: Option<String>,
+    ) -> Result<PublisherConfirm, String>
     where
         T: Serialize + Routable,
     {
         let json = serde_json::to_string(&event).or(Err("failed to serialize event".to_owned()))?;
 
+        let mut properties =
+            BasicProperties::default().with_content_type("application/json".into());
+
+        if let Some(message_id) = message_id {
+            properties = properties.with_message_id(message_id.into());
+        }
+
         self.publish(
             &self.options.email_events_exchange,
             event.routing_key().as_str(),
             json.as_bytes(),
-            BasicProperties::default().with_content_type("application/json".into()),
+            properties,
         )
         .await
     }
@@ -0,0 +1,13 @@
+use crate::config;
+use aws_sdk_sesv2::{config::Region, Client};
+
+/// creates a SES client for the configured region, using `aws_ses_endpoint` instead of the AWS endpoint if set
+pub async fn new_ses_client(cfg: &config::AppConfig) -> Client {
+    let mut aws_cfg_loader = aws_config::from_env().region(Region::new(cfg.aws_region.to_owned()));
+
+    if let Some(endpoint) = &cfg.aws_ses_endpoint {
+        aws_cfg_loader = aws_cfg_loader.endpoint_url(endpoint);
+    }
+
+    Client::new(&aws_cfg_loader.load().await)
+}
@@ -0,0 +1,212 @@
+//! SNS delivers messages at least once, so the same SES event may be received more than once,
+//! message ids seen in the last `ttl` are tracked to drop the duplicates
+
+use crate::storage::repository::Repository;
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tracing::error;
+
+#[derive(Debug, Default)]
+struct SeenMessages {
+    /// when each tracked message id was seen
+    ids: HashMap<String, Instant>,
+
+    /// message ids in the order they were seen, so expired ids are removed without scanning every tracked id
+    seen_order: VecDeque<(Instant, String)>,
+}
+
+impl SeenMessages {
+    fn remove_expired(&mut self, ttl: Duration) {
+        while let Some((seen_at, _)) = self.seen_order.front() {
+            if seen_at.elapsed() < ttl {
+                break;
+            }
+
+            if let Some((seen_at, id)) = self.seen_order.pop_front() {
+                // the id may have been forgotten and seen again after this entry was added
+                if self.ids.get(&id) == Some(&seen_at) {
+                    self.ids.remove(&id);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SnsMessageDeduplicator {
+    ttl: Duration,
+    seen: Mutex<SeenMessages>,
+
+    /// if set seen message ids are also stored, so duplicates are detected after restarts
+    repository: Option<Arc<dyn Repository>>,
+
+    duplicates_dropped: AtomicU64,
+}
+
+impl SnsMessageDeduplicator {
+    /// a `ttl` of zero disables deduplication
+    pub fn new(ttl: Duration, repository: Option<Arc<dyn Repository>>) -> SnsMessageDeduplicator {
+        SnsMessageDeduplicator {
+            ttl,
+            repository,
+            seen: Mutex::new(SeenMessages::default()),
+            duplicates_dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// records the message id as seen, returning true if it was already seen in the last `ttl`
+    pub async fn is_duplicate(&self, message_id: &str) -> bool {
+        if self.ttl.is_zero() {
+            return false;
+        }
+
+        let seen_in_memory = {
+            let mut seen = self.seen.lock().unwrap();
+            seen.remove_expired(self.ttl);
+
+            if seen.ids.contains_key(message_id) {
+                true
+            } else {
+                let now = Instant::now();
+
+                seen.ids.insert(message_id.to_owned(), now);
+                seen.seen_order.push_back((now, message_id.to_owned()));
+
+                false
+            }
+        };
+
+        let is_duplicate = match (&self.repository, seen_in_memory) {
+            (_, true) => true,
+            (None, false) => false,
+            (Some(repository), false) => {
+                let not_before = chrono::Duration::from_std(self.ttl)
+                    .ok()
+                    .and_then(|ttl| Utc::now().checked_sub_signed(ttl))
+                    .unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+                match repository
+                    .register_sns_message(message_id, not_before)
+                    .await
+                {
+                    Ok(is_new) => !is_new,
+                    Err(e) => {
+                        error!("failed to store SNS message id: {}", e);
+                        false
+                    }
+                }
+            }
+        };
+
+        if is_duplicate {
+            self.duplicates_dropped.fetch_add(1, Ordering::Relaxed);
+        }
+
+        is_duplicate
+    }
+
+    /// stops tracking a message id, so the message is handled again if redelivered (eg: after a failed publish)
+    pub async fn forget(&self, message_id: &str) {
+        self.seen.lock().unwrap().ids.remove(message_id);
+
+        if let Some(repository) = &self.repository {
+            if let Err(e) = repository.delete_sns_message(message_id).await {
+                error!("failed to delete SNS message id: {}", e)
+            }
+        }
+    }
+
+    /// amount of duplicated messages dropped since the service started
+    pub fn duplicates_dropped(&self) -> u64 {
+        self.duplicates_dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::sqlite::SqliteRepository;
+    use tokio::time::sleep;
+
+    #[tokio::test]
+    async fn message_seen_within_ttl_is_duplicate() {
+        let deduplicator = SnsMessageDeduplicator::new(Duration::from_secs(60), None);
+
+        assert!(!deduplicator.is_duplicate("message").await);
+        assert!(deduplicator.is_duplicate("message").await);
+        assert!(!deduplicator.is_duplicate("other").await);
+
+        assert_eq!(deduplicator.duplicates_dropped(), 1);
+    }
+
+    #[tokio::test]
+    async fn zero_ttl_disables_deduplication() {
+        let deduplicator = SnsMessageDeduplicator::new(Duration::ZERO, None);
+
+        assert!(!deduplicator.is_duplicate("message").await);
+        assert!(!deduplicator.is_duplicate("message").await);
+    }
+
+    #[tokio::test]
+    async fn message_ids_expire_after_ttl() {
+        let deduplicator = SnsMessageDeduplicator::new(Duration::from_millis(50), None);
+
+        assert!(!deduplicator.is_duplicate("message").await);
+        sleep(Duration::from_millis(60)).await;
+
+        assert!(!deduplicator.is_duplicate("message").await);
+        assert!(deduplicator.is_duplicate("message").await);
+    }
+
+    #[tokio::test]
+    async fn forgotten_message_is_handled_again() {
+        let deduplicator = SnsMessageDeduplicator::new(Duration::from_millis(100), None);
+
+        assert!(!deduplicator.is_duplicate("message").await);
+        deduplicator.forget("message").await;
+
+        sleep(Duration::from_millis(60)).await;
+        assert!(!deduplicator.is_duplicate("message").await);
+
+        // the entry of the first time it was seen expires, but not the one of the second time
+        sleep(Duration::from_millis(60)).await;
+        assert!(deduplicator.is_duplicate("message").await);
+    }
+
+    #[tokio::test]
+    async fn persisted_message_ids_are_shared_across_restarts() {
+        let repository: Arc<dyn Repository> = Arc::new(SqliteRepository::in_memory());
+        let ttl = Duration::from_secs(60);
+
+        let deduplicator = SnsMessageDeduplicator::new(ttl, Some(repository.clone()));
+        assert!(!deduplicator.is_duplicate("message").await);
+        assert!(!deduplicator.is_duplicate("failed").await);
+        deduplicator.forget("failed").await;
+
+        // a new deduplicator has nothing in memory, like after a restart
+        let restarted = SnsMessageDeduplicator::new(ttl, Some(repository));
+        assert!(restarted.is_duplicate("message").await);
+        assert!(!restarted.is_duplicate("failed").await);
+    }
+
+    #[tokio::test]
+    async fn persisted_message_ids_expire_after_ttl() {
+        let repository: Arc<dyn Repository> = Arc::new(SqliteRepository::in_memory());
+        let ttl = Duration::from_millis(50);
+
+        let deduplicator = SnsMessageDeduplicator::new(ttl, Some(repository.clone()));
+        assert!(!deduplicator.is_duplicate("message").await);
+
+        sleep(Duration::from_millis(60)).await;
+
+        let restarted = SnsMessageDeduplicator::new(ttl, Some(repository));
+        assert!(!restarted.is_duplicate("message").await);
+    }
+}
@@ -0,0 +1,255 @@
+use super::dedup::SnsMessageDeduplicator;
+use crate::{
+    config::EmailEventsFormat,
+    controller::dto::{
+        events::{Email, EmailEvent},
+        ses::{SesEvent, SnsNotification},
+        status::RecipientState,
+        suppression::{SuppressedAddress, SuppressionReason},
+    },
+    mail::{mailer::MAIL_REQUEST_UUID_TAG_NAME, resend::ResendPolicy},
+    queue::server::Server,
+    storage::repository::Repository,
+};
+use convert_case::{Case, Casing};
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+/// see: https://docs.aws.amazon.com/ses/latest/dg/notification-contents.html#bounce-types
+static PERMANENT_BOUNCE_TYPE: &str = "Permanent";
+
+pub fn get_email_event_from_sns_notification(
+    sns_notification: SnsNotification,
+) -> Result<EmailEvent, String> {
+    get_email_event_from_ses_message(&sns_notification.message)
+}
+
+/// converts the JSON of a SES event, as found in the `Message` of SNS notifications or
+/// delivered as is by SES event destinations such as Kinesis Firehose
+pub fn get_email_event_from_ses_message(message: &str) -> Result<EmailEvent, String> {
+    #[allow(clippy::bind_instead_of_map, clippy::to_string_in_format_args)]
+    let ses_evt = serde_json::from_str::<SesEvent>(message).or_else(|e| {
+        Err(format!(
+            "failed to parse message to SesEvent: {}",
+            e.to_string()
+        ))
+    })?;
+
+    let request_uuid = ses_evt
+        .mail
+        .tags
+        .get(MAIL_REQUEST_UUID_TAG_NAME)
+        .ok_or(format!(
+            "required tag: {} not present on mail tags",
+            MAIL_REQUEST_UUID_TAG_NAME
+        ))?
+        .first()
+        .ok_or(format!(
+            "required tag: {} is present but is empty",
+            MAIL_REQUEST_UUID_TAG_NAME
+        ))?
+        .to_owned();
+
+    let event_type = ses_evt
+        .event_type
+        .or(ses_evt.notification_type)
+        .ok_or("failed to get event type from ses event")?
+        .to_case(Case::Snake);
+
+    let err_msg = format!("object for event of type: {} not present", event_type);
+
+    let event = match event_type.as_str() {
+        "send" => Email::send(ses_evt.send.ok_or(err_msg)?),
+        "open" => Email::open(ses_evt.open.ok_or(err_msg)?),
+        "click" => Email::click(ses_evt.click.ok_or(err_msg)?),
+        "bounce" => Email::bounce(ses_evt.bounce.ok_or(err_msg)?),
+        "reject" => Email::reject(ses_evt.reject.ok_or(err_msg)?),
+        "failure" => Email::failure(ses_evt.failure.ok_or(err_msg)?),
+        "delivery" => Email::delivery(ses_evt.delivery.ok_or(err_msg)?),
+        "complaint" => Email::complaint(ses_evt.complaint.ok_or(err_msg)?),
+        "subscription" => Email::subscription(ses_evt.subscription.ok_or(err_msg)?),
+        "delivery_delay" => Email::delivery_delay(ses_evt.delivery_delay.ok_or(err_msg)?),
+        _ => return Err(format!("unknown event type: {}", event_type)),
+    };
+
+    Ok(EmailEvent {
+        event,
+        event_type,
+        request_uuid,
+        mail: ses_evt.mail,
+        recipient: None,
+    })
+}
+
+/// Stores and publishes email events received from SES, regardless of how they reached
+/// this service (SNS HTTP subscription or SQS queue)
+#[derive(Debug)]
+pub struct SesEventHandler {
+    queue_server: Arc<Server>,
+    repository: Arc<dyn Repository>,
+    deduplicator: SnsMessageDeduplicator,
+    events_format: EmailEventsFormat,
+
+    /// if raw events affecting many recipients are published as one event per recipient
+    split_by_recipient: bool,
+
+    resend_policy: Option<Arc<ResendPolicy>>,
+}
+
+impl SesEventHandler {
+    pub fn new(
+        queue_server: Arc<Server>,
+        repository: Arc<dyn Repository>,
+        deduplicator: SnsMessageDeduplicator,
+        events_format: EmailEventsFormat,
+        split_by_recipient: bool,
+        resend_policy: Option<Arc<ResendPolicy>>,
+    ) -> SesEventHandler {
+        SesEventHandler {
+            queue_server,
+            repository,
+            deduplicator,
+            events_format,
+            split_by_recipient,
+            resend_policy,
+        }
+    }
+
+    pub fn duplicates_dropped(&self) -> u64 {
+        self.deduplicator.duplicates_dropped()
+    }
+
+    /// handles the event unless the SNS message that carried it was already handled, events without
+    /// a SNS message id (eg: replayed from archives) are never considered duplicates
+    pub async fn handle(
+        &self,
+        email_event: EmailEvent,
+        sns_message_id: Option<&str>,
+    ) -> Result<(), String> {
+        let Some(sns_message_id) = sns_message_id else {
+            return self.store_and_publish(email_event, None).await;
+        };
+
+        if self.deduplicator.is_duplicate(sns_message_id).await {
+            println!("[SES] dropping duplicated SNS message: {}", sns_message_id);
+            return Ok(());
+        }
+
+        let result = self
+            .store_and_publish(email_event, Some(sns_message_id))
+            .await;
+
+        // the message must be handled again when redelivered, since its event was not published
+        if result.is_err() {
+            self.deduplicator.forget(sns_message_id).await;
+        }
+
+        result
+    }
+
+    /// publishes the event in the configured formats, then updates the state of its recipients, stores it, suppresses
+    /// and schedules resends for its recipients. Storage errors are only logged, so a error is returned only if the event
+    /// could not be published
+    async fn store_and_publish(
+        &self,
+        email_event: EmailEvent,
+        sns_message_id: Option<&str>,
+    ) -> Result<(), String> {
+        // the events are published in the same order when the message is redelivered, so each gets the same id
+        let mut published = 0;
+        let mut next_message_id = || {
+            published += 1;
+            sns_message_id.map(|id| format!("{}:{}", id, published - 1))
+        };
+
+        if self.events_format.publishes_normalized() {
+            for normalized_event in email_event.normalize() {
+                self.queue_server
+                    .publish_as_json_with_id(normalized_event, next_message_id())
+                    .await?;
+            }
+        }
+
+        if self.events_format.publishes_raw() {
+            if self.split_by_recipient {
+                for recipient_event in email_event.split_by_recipient() {
+                    self.queue_server
+                        .publish_as_json_with_id(recipient_event, next_message_id())
+                        .await?;
+                }
+            } else {
+                self.queue_server
+                    .publish_as_json_with_id(email_event.clone(), next_message_id())
+                    .await?;
+            }
+        }
+
+        // only once published, since events that fail to publish are handled again when redelivered
+        let recipient_state = RecipientState::from_ses_event_type(&email_event.event_type);
+        let request_uuid = email_event.request_uuid.parse::<Uuid>();
+
+        if let (Some(recipient_state), Ok(uuid)) = (recipient_state, request_uuid) {
+            let recipients = email_event.recipients();
+
+            if let Err(e) = self
+                .repository
+                .set_recipients_state(uuid, &recipients, recipient_state, None, None)
+                .await
+            {
+                error!("failed to store recipients state: {}", e)
+            }
+        }
+
+        if let Err(e) = self.repository.save_event(&email_event).await {
+            error!("failed to store ses event: {}", e)
+        }
+
+        self.update_suppressions(&email_event).await;
+
+        if let Some(resend_policy) = &self.resend_policy {
+            resend_policy.handle_event(&email_event).await;
+        }
+
+        Ok(())
+    }
+
+    /// suppresses recipients that permanently bounced or complained, so they are not sent emails again
+    async fn update_suppressions(&self, email_event: &EmailEvent) {
+        let suppressions: Vec<SuppressedAddress> = match &email_event.event {
+            Email::bounce(bounce) if bounce.bounce_type == PERMANENT_BOUNCE_TYPE => bounce
+                .bounced_recipients
+                .iter()
+                .map(|r| {
+                    SuppressedAddress::new(
+                        &r.email_address,
+                        SuppressionReason::Bounce,
+                        Some(format!("{}: {}", bounce.bounce_sub_type, r.diagnostic_code)),
+                    )
+                })
+                .collect(),
+            Email::complaint(complaint) => complaint
+                .complained_recipients
+                .iter()
+                .map(|r| {
+                    SuppressedAddress::new(
+                        &r.email_address,
+                        SuppressionReason::Complaint,
+                        Some(complaint.complaint_feedback_type.clone()),
+                    )
+                })
+                .collect(),
+            _ => return,
+        };
+
+        for suppression in suppressions {
+            match self.repository.add_suppression(&suppression).await {
+                Ok(_) => println!(
+                    "[SES] suppressed {} due to {}",
+                    suppression.email, suppression.reason
+                ),
+                Err(e) => error!("failed to store suppression: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Validation of the sender of requests against the identities verified on the SES account and the senders
+//! each tenant is allowed to use, so requests that SES would reject are rejected before sending any email
+//!
+//! see: https://docs.aws.amazon.com/ses/latest/dg/creating-identities.html
+
+use crate::{config, controller::validation::get_sender_address};
+use aws_sdk_sesv2::{types::VerificationStatus, Client};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+    time::Duration,
+};
+use tokio::time;
+use tracing::error;
+
+/// see: https://docs.aws.amazon.com/ses/latest/APIReference-V2/API_ListEmailIdentities.html
+static MAX_LIST_EMAIL_IDENTITIES_PAGE_SIZE: i32 = 1000;
+
+#[derive(Debug)]
+pub struct SenderIdentities {
+    aws_client: Client,
+    refresh_interval: Duration,
+    default_sender: String,
+
+    /// verified addresses and domains of the account, None until they are listed for the first time
+    verified: RwLock<Option<HashSet<String>>>,
+
+    /// addresses and domains each tenant can send from, tenants without entries can use any verified sender
+    allowed_senders: HashMap<String, HashSet<String>>,
+}
+
+impl SenderIdentities {
+    pub fn new(cfg: &config::AppConfig, aws_client: Client) -> Result<SenderIdentities, String> {
+        let mut allowed_senders: HashMap<String, HashSet<String>> = HashMap::new();
+
+        for entry in cfg.app_tenant_allowed_senders.iter().flatten() {
+            let (tenant, sender) = entry.split_once('=').ok_or(format!(
+                "invalid allowed sender, expected <tenant>=<address or domain>: {}",
+                entry
+            ))?;
+
+            allowed_senders
+                .entry(tenant.trim().to_lowercase())
+                .or_default()
+                .insert(sender.trim().to_lowercase());
+        }
+
+        Ok(SenderIdentities {
+            aws_client,
+            refresh_interval: Duration::from_secs(cfg.aws_ses_identities_refresh_secs),
+            default_sender: cfg.app_default_email_sender.to_owned(),
+            verified: RwLock::new(None),
+            allowed_senders,
+        })
+    }
+
+    /// lists the verified identities on startup and every `refresh_interval`, keeping the last known
+    /// identities if they cannot be listed, a zero interval disables the validation against them
+    pub async fn run(&self) {
+        if self.refresh_interval.is_zero() {
+            return;
+        }
+
+        let mut interval = time::interval(self.refresh_interval);
+
+        loop {
+            interval.tick().await;
+
+            match self.list_verified_identities().await {
+                Ok(identities) => {
+                    let previous = self.verified.write().unwrap().replace(identities.clone());
+
+                    if previous.as_ref() != Some(&identities) {
+                        println!("[SES] {} verified sender identities", identities.len());
+                    }
+                }
+                Err(e) => error!("failed to list SES identities: {}", e),
+            }
+        }
+    }
+
+    /// the reason to reject a request of the tenant from the sender (or the default sender), None if it can be sent
+    ///
+    /// a domain also covers its subdomains, so `example.com` allows `news@mail.example.com`. Senders are not
+    /// validated against SES until the identities are listed, so SES errors do not reject every request
+    pub fn get_rejection_reason(
+        &self,
+        tenant: Option<&str>,
+        sender: Option<&str>,
+    ) -> Option<String> {
+        let address = get_sender_address(sender.unwrap_or(&self.default_sender));
+        let identities = get_identities(&address);
+
+        if let Some(allowed) = tenant.and_then(|t| self.allowed_senders.get(t)) {
+            if !identities.iter().any(|i| allowed.contains(i)) {
+                return Some(format!(
+                    "sender {} is not allowed for tenant {}",
+                    address,
+                    tenant.unwrap_or_default()
+                ));
+            }
+        }
+
+        let verified = self.verified.read().unwrap();
+
+        match verified.as_ref() {
+            Some(verified) if !identities.iter().any(|i| verified.contains(i)) => {
+                Some(format!("sender {} is not a verified SES identity", address))
+            }
+            _ => None,
+        }
+    }
+
+    async fn list_verified_identities(&self) -> Result<HashSet<String>, String> {
+        let mut identities = HashSet::new();
+        let mut next_token = None;
+
+        loop {
+            let output = self
+                .aws_client
+                .list_email_identities()
+                .page_size(MAX_LIST_EMAIL_IDENTITIES_PAGE_SIZE)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            for identity in output.email_identities().unwrap_or_default() {
+                if identity.verification_status() != Some(&VerificationStatus::Success) {
+                    continue;
+                }
+
+                if let Some(name) = identity.identity_name() {
+                    identities.insert(name.to_lowercase());
+                }
+            }
+
+            next_token = output.next_token().map(|t| t.to_owned());
+
+            if next_token.is_none() {
+                return Ok(identities);
+            }
+        }
+    }
+}
+
+/// the address followed by its domain and parent domains, eg: `a@mail.example.com`, `mail.example.com`, `example.com`
+fn get_identities(address: &str) -> Vec<String> {
+    let mut identities = vec![address.to_owned()];
+
+    if let Some((_, mut domain)) = address.rsplit_once('@') {
+        loop {
+            identities.push(domain.to_owned());
+
+            match domain.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => domain = parent,
+                _ => break,
+            }
+        }
+    }
+
+    identities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_sesv2::config::{Config, Region};
+
+    /// identities verified on the account, as listed by `list_verified_identities`
+    fn sender_identities(verified: &[&str]) -> SenderIdentities {
+        let cfg: config::AppConfig = envy::from_iter([
+            (
+                "APP_TENANT_ALLOWED_SENDERS".to_owned(),
+                "marketing=news.example.com,billing=billing@example.com".to_owned(),
+            ),
+            (
+                "APP_DEFAULT_EMAIL_SENDER".to_owned(),
+                "Mailer <mailer@example.com>".to_owned(),
+            ),
+        ])
+        .unwrap();
+
+        // the client is never called, since the verified identities are set directly
+        let aws_client =
+            Client::from_conf(Config::builder().region(Region::new("us-east-1")).build());
+
+        let identities = SenderIdentities::new(&cfg, aws_client).unwrap();
+        *identities.verified.write().unwrap() =
+            Some(verified.iter().map(|i| i.to_string()).collect());
+
+        identities
+    }
+
+    #[test]
+    fn domain_identity_allows_its_addresses_and_subdomains() {
+        let identities = sender_identities(&["example.com"]);
+
+        for sender in [
+            "a@example.com",
+            "News <news@mail.example.com>",
+            "A@EXAMPLE.COM",
+        ] {
+            assert_eq!(
+                identities.get_rejection_reason(None, Some(sender)),
+                None,
+                "{}",
+                sender
+            );
+        }
+
+        assert!(identities
+            .get_rejection_reason(None, Some("a@example.org"))
+            .is_some());
+    }
+
+    #[test]
+    fn address_identity_allows_only_the_address() {
+        let identities = sender_identities(&["mailer@example.com"]);
+
+        assert_eq!(identities.get_rejection_reason(None, None), None);
+        assert_eq!(
+            identities.get_rejection_reason(None, Some("mailer@example.com")),
+            None
+        );
+        assert!(identities
+            .get_rejection_reason(None, Some("other@example.com"))
+            .is_some());
+    }
+
+    #[test]
+    fn unverified_identity_is_rejected() {
+        // pending or failed identities are not listed as verified
+        let identities = sender_identities(&["example.com"]);
+
+        assert_eq!(
+            identities.get_rejection_reason(None, Some("a@unverified.org")),
+            Some("sender a@unverified.org is not a verified SES identity".to_owned())
+        );
+    }
+
+    #[test]
+    fn senders_are_allowed_until_identities_are_listed() {
+        let identities = sender_identities(&[]);
+        *identities.verified.write().unwrap() = None;
+
+        assert_eq!(
+            identities.get_rejection_reason(None, Some("a@unverified.org")),
+            None
+        );
+    }
+
+    #[test]
+    fn tenants_are_limited_to_their_allowed_senders() {
+        let identities = sender_identities(&["example.com"]);
+
+        assert_eq!(
+            identities.get_rejection_reason(Some("marketing"), Some("a@news.example.com")),
+            None
+        );
+        assert_eq!(
+            identities.get_rejection_reason(Some("billing"), Some("billing@example.com")),
+            None
+        );
+        assert_eq!(
+            identities.get_rejection_reason(Some("marketing"), Some("billing@example.com")),
+            Some("sender billing@example.com is not allowed for tenant marketing".to_owned())
+        );
+
+        // tenants without allowed senders can use any verified sender
+        assert_eq!(
+            identities.get_rejection_reason(Some("other"), Some("billing@example.com")),
+            None
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! Discovery of the SES account sending quota, so the rate limit does not need to be configured by hand
+//! and the service stops sending before the 24 hour quota is exceeded
+//!
+//! see: https://docs.aws.amazon.com/ses/latest/dg/manage-sending-quotas.html
+
+use crate::{
+    config, controller::dto::events::SesQuotaWarningEvent, mail::rate_limiter::SendRateLimiter,
+    queue::server::Server,
+};
+use aws_sdk_sesv2::{types::SendQuota, Client};
+use std::{num::NonZeroU32, sync::Arc, time::Duration};
+use tokio::time;
+use tracing::error;
+
+/// a warning is published when the usage crosses the warning threshold or sending is paused/resumed, not on every check
+#[derive(Debug, Default)]
+struct QuotaAlerts {
+    /// if the warning for the current threshold crossing was published
+    warned: bool,
+    paused: bool,
+}
+
+impl QuotaAlerts {
+    /// updates the state with the usage of a check, returning if sending must be paused or resumed and if a warning
+    /// must be published, the warning is returned on every check until it is marked as published
+    fn update(
+        &mut self,
+        usage_percent: f64,
+        warning_percent: f64,
+        pause_percent: f64,
+    ) -> (Option<bool>, bool) {
+        let should_pause = usage_percent >= pause_percent;
+        let mut paused_changed = None;
+
+        if should_pause != self.paused {
+            self.paused = should_pause;
+            self.warned = false;
+            paused_changed = Some(should_pause);
+        }
+
+        if usage_percent < warning_percent {
+            self.warned = false;
+        }
+
+        (
+            paused_changed,
+            usage_percent >= warning_percent && !self.warned,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct SesQuotaMonitor {
+    aws_client: Client,
+    rate_limiter: Arc<dyn SendRateLimiter>,
+    server: Arc<Server>,
+    refresh_interval: Duration,
+    warning_percent: f64,
+    pause_percent: f64,
+}
+
+impl SesQuotaMonitor {
+    pub fn new(
+        cfg: &config::AppConfig,
+        aws_client: Client,
+        rate_limiter: Arc<dyn SendRateLimiter>,
+        server: Arc<Server>,
+    ) -> SesQuotaMonitor {
+        SesQuotaMonitor {
+            aws_client,
+            rate_limiter,
+            server,
+            refresh_interval: Duration::from_secs(cfg.aws_ses_quota_refresh_secs),
+            warning_percent: cfg.aws_ses_quota_warning_percent.into(),
+            pause_percent: cfg.aws_ses_quota_pause_percent.into(),
+        }
+    }
+
+    /// reads the quota on startup and every `refresh_interval`, if it cannot be read the last known
+    /// rate is kept, which is `aws_ses_max_emails_per_second` until the quota is read for the first time
+    pub async fn run(&self) {
+        let mut interval = time::interval(self.refresh_interval);
+        let mut alerts = QuotaAlerts::default();
+
+        loop {
+            interval.tick().await;
+
+            let quota = match self.get_send_quota().await {
+                Ok(quota) => quota,
+                Err(e) => {
+                    error!(
+                        "failed to get SES sending quota, keeping {} emails per second: {}",
+                        self.rate_limiter.max_per_second(),
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            self.update_rate_limit(&quota);
+
+            let usage_percent = get_usage_percent(&quota);
+
+            let (paused_changed, should_warn) =
+                alerts.update(usage_percent, self.warning_percent, self.pause_percent);
+
+            if let Some(paused) = paused_changed {
+                self.server.set_sending_paused(paused);
+            }
+
+            if !should_warn {
+                continue;
+            }
+
+            println!(
+                "[SES] {:.1}% of the 24 hour sending quota used ({} of {})",
+                usage_percent,
+                quota.sent_last24_hours(),
+                quota.max24_hour_send()
+            );
+
+            let warning_event = SesQuotaWarningEvent::new(
+                quota.max24_hour_send(),
+                quota.sent_last24_hours(),
+                quota.max_send_rate(),
+                alerts.paused,
+            );
+
+            match self.server.publish_as_json(warning_event).await {
+                Ok(_) => alerts.warned = true,
+                Err(e) => error!("failed to publish SES quota warning: {}", e),
+            }
+        }
+    }
+
+    async fn get_send_quota(&self) -> Result<SendQuota, String> {
+        self.aws_client
+            .get_account()
+            .send()
+            .await
+            .map_err(|e| e.to_string())?
+            .send_quota()
+            .cloned()
+            .ok_or("GetAccount response without send quota".to_owned())
+    }
+
+    fn update_rate_limit(&self, quota: &SendQuota) {
+        // SES allows fractional rates, the limiter only whole ones, so the rate is rounded down (but at least 1)
+        let max_per_second =
+            NonZeroU32::new(quota.max_send_rate().floor() as u32).unwrap_or(NonZeroU32::MIN);
+
+        if max_per_second != self.rate_limiter.max_per_second() {
+            println!(
+                "[SES] sending rate changed from {} to {} emails per second",
+                self.rate_limiter.max_per_second(),
+                max_per_second
+            );
+
+            self.rate_limiter.set_max_per_second(max_per_second);
+        }
+    }
+}
+
+/// percentage of the 24 hour quota used, accounts with a unlimited quota have a `Max24HourSend` of -1
+fn get_usage_percent(quota: &SendQuota) -> f64 {
+    if quota.max24_hour_send() <= 0.0 {
+        return 0.0;
+    }
+
+    quota.sent_last24_hours() / quota.max24_hour_send() * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mail::rate_limiter::InMemoryRateLimiter, queue::server::Routable};
+    use aws_sdk_sesv2::config::{Config, Region};
+    use tokio::sync::mpsc;
+
+    fn quota(max_24_hour_send: f64, sent_last_24_hours: f64, max_send_rate: f64) -> SendQuota {
+        SendQuota::builder()
+            .max24_hour_send(max_24_hour_send)
+            .sent_last24_hours(sent_last_24_hours)
+            .max_send_rate(max_send_rate)
+            .build()
+    }
+
+    #[test]
+    fn warns_once_per_threshold_crossing() {
+        let mut alerts = QuotaAlerts::default();
+
+        assert_eq!(alerts.update(50.0, 80.0, 95.0), (None, false));
+        assert_eq!(alerts.update(85.0, 80.0, 95.0), (None, true));
+        alerts.warned = true;
+
+        assert_eq!(alerts.update(90.0, 80.0, 95.0), (None, false));
+
+        // crossing the threshold again after the usage went down warns again
+        assert_eq!(alerts.update(70.0, 80.0, 95.0), (None, false));
+        assert_eq!(alerts.update(81.0, 80.0, 95.0), (None, true));
+    }
+
+    #[test]
+    fn warning_is_repeated_until_published() {
+        let mut alerts = QuotaAlerts::default();
+
+        assert_eq!(alerts.update(85.0, 80.0, 95.0), (None, true));
+        assert_eq!(alerts.update(86.0, 80.0, 95.0), (None, true));
+    }
+
+    #[test]
+    fn pausing_and_resuming_warn_again() {
+        let mut alerts = QuotaAlerts::default();
+
+        assert_eq!(alerts.update(85.0, 80.0, 95.0), (None, true));
+        alerts.warned = true;
+
+        assert_eq!(alerts.update(96.0, 80.0, 95.0), (Some(true), true));
+        alerts.warned = true;
+
+        assert_eq!(alerts.update(97.0, 80.0, 95.0), (None, false));
+        assert_eq!(alerts.update(90.0, 80.0, 95.0), (Some(false), true));
+        assert!(!alerts.paused);
+    }
+
+    #[test]
+    fn unlimited_quota_is_never_used() {
+        assert_eq!(get_usage_percent(&quota(-1.0, 5000.0, 14.0)), 0.0);
+        assert_eq!(get_usage_percent(&quota(200.0, 50.0, 1.0)), 25.0);
+    }
+
+    #[test]
+    fn warning_event_has_the_quota() {
+        let quota = quota(200.0, 190.0, 1.0);
+
+        let event = SesQuotaWarningEvent::new(
+            quota.max24_hour_send(),
+            quota.sent_last24_hours(),
+            quota.max_send_rate(),
+            true,
+        );
+
+        assert_eq!(event.routing_key(), "quota.ses.warning");
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["max_24_hour_send"], 200.0);
+        assert_eq!(json["sent_last_24_hours"], 190.0);
+        assert_eq!(json["max_send_rate"], 1.0);
+        assert_eq!(json["sending_paused"], true);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_follows_the_max_send_rate() {
+        let cfg: config::AppConfig = envy::from_iter(Vec::<(String, String)>::new()).unwrap();
+        let rate_limiter = Arc::new(InMemoryRateLimiter::new(NonZeroU32::MIN));
+
+        let monitor = SesQuotaMonitor::new(
+            &cfg,
+            Client::from_conf(Config::builder().region(Region::new("us-east-1")).build()),
+            rate_limiter.clone(),
+            Arc::new(Server::new(&cfg, mpsc::unbounded_channel().0)),
+        );
+
+        monitor.update_rate_limit(&quota(50_000.0, 0.0, 14.0));
+        assert_eq!(rate_limiter.max_per_second().get(), 14);
+
+        // fractional rates are rounded down, but never below 1
+        monitor.update_rate_limit(&quota(50_000.0, 0.0, 28.9));
+        assert_eq!(rate_limiter.max_per_second().get(), 28);
+
+        monitor.update_rate_limit(&quota(200.0, 0.0, 0.5));
+        assert_eq!(rate_limiter.max_per_second().get(), 1);
+    }
+}
@@ -0,0 +1,364 @@
+//! Synchronization between the local suppression list and the SES account suppression list
+//!
+//! see: https://docs.aws.amazon.com/ses/latest/dg/sending-email-suppression-list.html
+
+use crate::{
+    controller::dto::suppression::{
+        normalize_email, SesSuppressionSyncMode, SesSuppressionSyncReport, SuppressedAddress,
+        SuppressionReason,
+    },
+    storage::repository::Repository,
+};
+use aws_sdk_sesv2::{types::SuppressionListReason, Client};
+use std::{collections::HashMap, sync::Arc};
+
+/// see: https://docs.aws.amazon.com/ses/latest/APIReference-V2/API_ListSuppressedDestinations.html
+static MAX_LIST_SUPPRESSED_DESTINATIONS_PAGE_SIZE: i32 = 1000;
+
+/// detail of the local entries created from SES entries
+static IMPORTED_SUPPRESSION_DETAIL: &str = "imported from SES account suppression list";
+
+/// changes to apply on each list, in email order
+#[derive(Debug, Default, PartialEq)]
+struct SyncChanges {
+    /// SES entries to add to the local list
+    import: Vec<(String, SuppressionReason)>,
+
+    /// local entries to add to the SES list
+    export: Vec<(String, SuppressionReason)>,
+
+    remove_locally: Vec<String>,
+    remove_from_ses: Vec<String>,
+}
+
+impl SyncChanges {
+    /// compares both lists, keyed by normalized email, without changing them
+    fn new(
+        mode: SesSuppressionSyncMode,
+        prune: bool,
+        ses_entries: &HashMap<String, SuppressionReason>,
+        local_entries: &HashMap<String, SuppressionReason>,
+    ) -> SyncChanges {
+        let missing_on = |source: &HashMap<String, SuppressionReason>,
+                          target: &HashMap<String, SuppressionReason>| {
+            let mut missing: Vec<_> = source
+                .iter()
+                .filter(|(email, _)| !target.contains_key(*email))
+                .map(|(email, reason)| (email.clone(), *reason))
+                .collect();
+
+            missing.sort_by(|a, b| a.0.cmp(&b.0));
+            missing
+        };
+
+        let emails = |entries: Vec<(String, SuppressionReason)>| -> Vec<String> {
+            entries.into_iter().map(|(email, _)| email).collect()
+        };
+
+        let mut changes = SyncChanges::default();
+
+        if matches!(
+            mode,
+            SesSuppressionSyncMode::Import | SesSuppressionSyncMode::Reconcile
+        ) {
+            changes.import = missing_on(ses_entries, local_entries);
+        }
+
+        if matches!(
+            mode,
+            SesSuppressionSyncMode::Export | SesSuppressionSyncMode::Reconcile
+        ) {
+            changes.export = missing_on(local_entries, ses_entries);
+        }
+
+        if prune && mode == SesSuppressionSyncMode::Import {
+            changes.remove_locally = emails(missing_on(local_entries, ses_entries));
+        }
+
+        if prune && mode == SesSuppressionSyncMode::Export {
+            changes.remove_from_ses = emails(missing_on(ses_entries, local_entries));
+        }
+
+        changes
+    }
+}
+
+#[derive(Debug)]
+pub struct SesSuppressionSync {
+    aws_client: Client,
+    repository: Arc<dyn Repository>,
+}
+
+impl SesSuppressionSync {
+    pub fn new(aws_client: Client, repository: Arc<dyn Repository>) -> SesSuppressionSync {
+        SesSuppressionSync {
+            aws_client,
+            repository,
+        }
+    }
+
+    /// runs the synchronization, with `prune` entries missing on the source list are deleted from the target list,
+    /// pruning is only applied on the import and export modes since reconciling keeps the union of both lists
+    pub async fn run(
+        &self,
+        mode: SesSuppressionSyncMode,
+        prune: bool,
+    ) -> Result<SesSuppressionSyncReport, String> {
+        let mut report = SesSuppressionSyncReport::default();
+
+        let ses_entries = self.list_ses_suppressions().await?;
+
+        let local_entries: HashMap<String, SuppressionReason> = self
+            .repository
+            .list_suppressions()
+            .await?
+            .into_iter()
+            .map(|s| (s.email, s.reason))
+            .collect();
+
+        let changes = SyncChanges::new(mode, prune, &ses_entries, &local_entries);
+
+        for (email, reason) in &changes.import {
+            let suppression = SuppressedAddress::new(
+                email,
+                *reason,
+                Some(IMPORTED_SUPPRESSION_DETAIL.to_owned()),
+            );
+
+            self.repository.add_suppression(&suppression).await?;
+            report.imported += 1;
+        }
+
+        for email in &changes.remove_locally {
+            self.repository.remove_suppression(email).await?;
+            report.removed_locally += 1;
+        }
+
+        for (email, reason) in &changes.export {
+            self.put_ses_suppression(email, *reason).await?;
+            report.exported += 1;
+        }
+
+        for email in &changes.remove_from_ses {
+            self.delete_ses_suppression(email).await?;
+            report.removed_from_ses += 1;
+        }
+
+        println!(
+            "[SES] suppression list {} finished, imported: {}, exported: {}, removed locally: {}, removed from SES: {}",
+            mode,
+            report.imported,
+            report.exported,
+            report.removed_locally,
+            report.removed_from_ses
+        );
+
+        Ok(report)
+    }
+
+    /// lists every address of the SES account suppression list, with its normalized email as key
+    async fn list_ses_suppressions(&self) -> Result<HashMap<String, SuppressionReason>, String> {
+        let mut entries = HashMap::new();
+        let mut next_token = None;
+
+        loop {
+            let output = self
+                .aws_client
+                .list_suppressed_destinations()
+                .page_size(MAX_LIST_SUPPRESSED_DESTINATIONS_PAGE_SIZE)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|e| format!("failed to list SES suppressed destinations: {}", e))?;
+
+            for summary in output
+                .suppressed_destination_summaries()
+                .unwrap_or_default()
+            {
+                let Some(email) = summary.email_address() else {
+                    continue;
+                };
+
+                let reason = match summary.reason() {
+                    Some(SuppressionListReason::Complaint) => SuppressionReason::Complaint,
+                    _ => SuppressionReason::Bounce,
+                };
+
+                entries.insert(normalize_email(email), reason);
+            }
+
+            next_token = output.next_token().map(|t| t.to_owned());
+
+            if next_token.is_none() {
+                return Ok(entries);
+            }
+        }
+    }
+
+    /// SES only accepts bounce and complaint reasons, manually suppressed addresses are exported as bounces
+    async fn put_ses_suppression(
+        &self,
+        email: &str,
+        reason: SuppressionReason,
+    ) -> Result<(), String> {
+        let ses_reason = match reason {
+            SuppressionReason::Complaint => SuppressionListReason::Complaint,
+            SuppressionReason::Bounce | SuppressionReason::Manual => SuppressionListReason::Bounce,
+        };
+
+        self.aws_client
+            .put_suppressed_destination()
+            .email_address(email)
+            .reason(ses_reason)
+            .send()
+            .await
+            .map_err(|e| format!("failed to put SES suppressed destination {}: {}", email, e))?;
+
+        Ok(())
+    }
+
+    async fn delete_ses_suppression(&self, email: &str) -> Result<(), String> {
+        self.aws_client
+            .delete_suppressed_destination()
+            .email_address(email)
+            .send()
+            .await
+            .map_err(|e| {
+                format!(
+                    "failed to delete SES suppressed destination {}: {}",
+                    email, e
+                )
+            })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(entries: &[(&str, SuppressionReason)]) -> HashMap<String, SuppressionReason> {
+        entries
+            .iter()
+            .map(|(email, reason)| (email.to_string(), *reason))
+            .collect()
+    }
+
+    fn lists() -> (
+        HashMap<String, SuppressionReason>,
+        HashMap<String, SuppressionReason>,
+    ) {
+        let ses_entries = entries(&[
+            ("both@example.com", SuppressionReason::Bounce),
+            ("ses-b@example.com", SuppressionReason::Complaint),
+            ("ses-a@example.com", SuppressionReason::Bounce),
+        ]);
+
+        let local_entries = entries(&[
+            ("both@example.com", SuppressionReason::Manual),
+            ("local@example.com", SuppressionReason::Manual),
+        ]);
+
+        (ses_entries, local_entries)
+    }
+
+    #[test]
+    fn import_adds_ses_entries_missing_locally() {
+        let (ses_entries, local_entries) = lists();
+
+        let changes = SyncChanges::new(
+            SesSuppressionSyncMode::Import,
+            false,
+            &ses_entries,
+            &local_entries,
+        );
+
+        assert_eq!(
+            changes,
+            SyncChanges {
+                import: vec![
+                    ("ses-a@example.com".to_owned(), SuppressionReason::Bounce),
+                    ("ses-b@example.com".to_owned(), SuppressionReason::Complaint),
+                ],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn import_with_prune_removes_local_entries_missing_on_ses() {
+        let (ses_entries, local_entries) = lists();
+
+        let changes = SyncChanges::new(
+            SesSuppressionSyncMode::Import,
+            true,
+            &ses_entries,
+            &local_entries,
+        );
+
+        assert_eq!(changes.import.len(), 2);
+        assert_eq!(changes.remove_locally, vec!["local@example.com"]);
+        assert!(changes.export.is_empty());
+        assert!(changes.remove_from_ses.is_empty());
+    }
+
+    #[test]
+    fn export_adds_local_entries_missing_on_ses() {
+        let (ses_entries, local_entries) = lists();
+
+        let changes = SyncChanges::new(
+            SesSuppressionSyncMode::Export,
+            false,
+            &ses_entries,
+            &local_entries,
+        );
+
+        assert_eq!(
+            changes,
+            SyncChanges {
+                export: vec![("local@example.com".to_owned(), SuppressionReason::Manual)],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn export_with_prune_removes_ses_entries_missing_locally() {
+        let (ses_entries, local_entries) = lists();
+
+        let changes = SyncChanges::new(
+            SesSuppressionSyncMode::Export,
+            true,
+            &ses_entries,
+            &local_entries,
+        );
+
+        assert_eq!(changes.export.len(), 1);
+        assert_eq!(
+            changes.remove_from_ses,
+            vec!["ses-a@example.com", "ses-b@example.com"]
+        );
+        assert!(changes.import.is_empty());
+        assert!(changes.remove_locally.is_empty());
+    }
+
+    #[test]
+    fn reconcile_keeps_the_union_of_both_lists_even_with_prune() {
+        let (ses_entries, local_entries) = lists();
+
+        for prune in [false, true] {
+            let changes = SyncChanges::new(
+                SesSuppressionSyncMode::Reconcile,
+                prune,
+                &ses_entries,
+                &local_entries,
+            );
+
+            assert_eq!(changes.import.len(), 2);
+            assert_eq!(changes.export.len(), 1);
+            assert!(changes.remove_locally.is_empty());
+            assert!(changes.remove_from_ses.is_empty());
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Management of the email templates stored in the SES account, which requests can reference by name
+//!
+//! see: https://docs.aws.amazon.com/ses/latest/dg/send-personalized-email-api.html
+
+use crate::controller::dto::template::{EmailTemplate, EmailTemplateMetadata};
+use aws_sdk_sesv2::{error::SdkError, types::EmailTemplateContent, Client};
+use chrono::DateTime;
+use std::collections::HashMap;
+
+/// see: https://docs.aws.amazon.com/ses/latest/APIReference-V2/API_ListEmailTemplates.html
+static MAX_LIST_EMAIL_TEMPLATES_PAGE_SIZE: i32 = 100;
+
+#[derive(Debug)]
+pub struct SesTemplates {
+    aws_client: Client,
+}
+
+impl SesTemplates {
+    pub fn new(aws_client: Client) -> SesTemplates {
+        SesTemplates { aws_client }
+    }
+
+    /// creates the template, returning false if a template with the same name already exists
+    pub async fn create(&self, template: &EmailTemplate) -> Result<bool, String> {
+        match self
+            .aws_client
+            .create_email_template()
+            .template_name(&template.name)
+            .template_content(to_template_content(template))
+            .send()
+            .await
+        {
+            Ok(_) => {}
+            Err(SdkError::ServiceError(e)) if e.err().is_already_exists_exception() => {
+                return Ok(false)
+            }
+            Err(e) => return Err(format!("failed to create SES template: {}", e)),
+        }
+
+        println!("[SES] created template {}", template.name);
+
+        Ok(true)
+    }
+
+    /// replaces the content of the template, returning false if it does not exist
+    pub async fn update(&self, template: &EmailTemplate) -> Result<bool, String> {
+        match self
+            .aws_client
+            .update_email_template()
+            .template_name(&template.name)
+            .template_content(to_template_content(template))
+            .send()
+            .await
+        {
+            Ok(_) => {}
+            Err(SdkError::ServiceError(e)) if e.err().is_not_found_exception() => return Ok(false),
+            Err(e) => return Err(format!("failed to update SES template: {}", e)),
+        }
+
+        println!("[SES] updated template {}", template.name);
+
+        Ok(true)
+    }
+
+    /// the template with the given name, None if it does not exist
+    pub async fn get(&self, name: &str) -> Result<Option<EmailTemplate>, String> {
+        let output = match self
+            .aws_client
+            .get_email_template()
+            .template_name(name)
+            .send()
+            .await
+        {
+            Ok(output) => output,
+            Err(SdkError::ServiceError(e)) if e.err().is_not_found_exception() => return Ok(None),
+            Err(e) => return Err(format!("failed to get SES template: {}", e)),
+        };
+
+        let content = output.template_content();
+
+        Ok(Some(EmailTemplate {
+            name: output.template_name().unwrap_or(name).to_owned(),
+            subject: content
+                .and_then(|c| c.subject())
+                .unwrap_or_default()
+                .to_owned(),
+            html: content.and_then(|c| c.html()).map(|h| h.to_owned()),
+            text: content.and_then(|c| c.text()).map(|t| t.to_owned()),
+        }))
+    }
+
+    /// every template of the SES account, without their content
+    pub async fn list(&self) -> Result<Vec<EmailTemplateMetadata>, String> {
+        let mut templates = vec![];
+        let mut next_token = None;
+
+        loop {
+            let output = self
+                .aws_client
+                .list_email_templates()
+                .page_size(MAX_LIST_EMAIL_TEMPLATES_PAGE_SIZE)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|e| format!("failed to list SES templates: {}", e))?;
+
+            for metadata in output.templates_metadata().unwrap_or_default() {
+                let Some(name) = metadata.template_name() else {
+                    continue;
+                };
+
+                templates.push(EmailTemplateMetadata {
+                    name: name.to_owned(),
+                    created_at: metadata
+                        .created_timestamp()
+                        .and_then(|t| DateTime::from_timestamp(t.secs(), t.subsec_nanos())),
+                });
+            }
+
+            next_token = output.next_token().map(|t| t.to_owned());
+
+            if next_token.is_none() {
+                return Ok(templates);
+            }
+        }
+    }
+
+    /// deletes the template, returning false if it does not exist
+    pub async fn delete(&self, name: &str) -> Result<bool, String> {
+        match self
+            .aws_client
+            .delete_email_template()
+            .template_name(name)
+            .send()
+            .await
+        {
+            Ok(_) => {
+                println!("[SES] deleted template {}", name);
+                Ok(true)
+            }
+            Err(SdkError::ServiceError(e)) if e.err().is_not_found_exception() => Ok(false),
+            Err(e) => Err(format!("failed to delete SES template: {}", e)),
+        }
+    }
+
+    /// renders the template with the replacements, returning the MIME message SES would send or None
+    /// if the template does not exist, fails if a replacement used by the template is missing
+    pub async fn test_render(
+        &self,
+        name: &str,
+        replacements: &HashMap<String, String>,
+    ) -> Result<Option<String>, String> {
+        let template_data = serde_json::to_string(replacements).map_err(|e| e.to_string())?;
+
+        match self
+            .aws_client
+            .test_render_email_template()
+            .template_name(name)
+            .template_data(template_data)
+            .send()
+            .await
+        {
+            Ok(output) => Ok(Some(
+                output.rendered_template().unwrap_or_default().to_owned(),
+            )),
+            Err(SdkError::ServiceError(e)) if e.err().is_not_found_exception() => Ok(None),
+            Err(e) => Err(format!("failed to render SES template: {}", e)),
+        }
+    }
+}
+
+fn to_template_content(template: &EmailTemplate) -> EmailTemplateContent {
+    EmailTemplateContent::builder()
+        .subject(&template.subject)
+        .set_html(template.html.clone())
+        .set_text(template.text.clone())
+        .build()
+}
@@ -0,0 +1,176 @@
+use crate::config;
+use aws_config::timeout::TimeoutConfig;
+use aws_sdk_sqs::{config::Region, Client};
+use std::time::Duration;
+
+/// the long polling wait time is added to this timeout for ReceiveMessage calls
+static SQS_OPERATION_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug)]
+pub struct SqsMessage {
+    pub message_id: String,
+
+    pub receipt_handle: String,
+
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct SqsClient {
+    aws_client: Client,
+}
+
+impl SqsClient {
+    /// creates a SQS client for the configured region, using `aws_sqs_endpoint` instead of the AWS endpoint if set
+    pub async fn new(cfg: &config::AppConfig) -> SqsClient {
+        let mut aws_cfg_loader = aws_config::from_env()
+            .region(Region::new(cfg.aws_region.to_owned()))
+            .timeout_config(
+                TimeoutConfig::builder()
+                    .operation_timeout(Duration::from_secs(
+                        SQS_OPERATION_TIMEOUT_SECS + cfg.aws_sqs_wait_time_secs as u64,
+                    ))
+                    .build(),
+            );
+
+        if let Some(endpoint) = &cfg.aws_sqs_endpoint {
+            aws_cfg_loader = aws_cfg_loader.endpoint_url(endpoint);
+        }
+
+        SqsClient {
+            aws_client: Client::new(&aws_cfg_loader.load().await),
+        }
+    }
+
+    /// long polls the queue for up to `wait_time_secs`, returning at most `max_messages` messages
+    pub async fn receive_messages(
+        &self,
+        queue_url: &str,
+        max_messages: u8,
+        wait_time_secs: u8,
+    ) -> Result<Vec<SqsMessage>, String> {
+        let output = self
+            .aws_client
+            .receive_message()
+            .queue_url(queue_url)
+            .max_number_of_messages(max_messages.into())
+            .wait_time_seconds(wait_time_secs.into())
+            .send()
+            .await
+            .map_err(|e| format!("SQS ReceiveMessage failed: {}", e))?;
+
+        // messages without a receipt handle cannot be deleted, so they are left for the visibility timeout
+        Ok(output
+            .messages()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|message| {
+                Some(SqsMessage {
+                    message_id: message.message_id().unwrap_or_default().to_owned(),
+                    receipt_handle: message.receipt_handle()?.to_owned(),
+                    body: message.body().unwrap_or_default().to_owned(),
+                })
+            })
+            .collect())
+    }
+
+    pub async fn delete_message(
+        &self,
+        queue_url: &str,
+        receipt_handle: &str,
+    ) -> Result<(), String> {
+        self.aws_client
+            .delete_message()
+            .queue_url(queue_url)
+            .receipt_handle(receipt_handle)
+            .send()
+            .await
+            .map_err(|e| format!("SQS DeleteMessage failed: {}", e))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// runs against a local SQS, eg: `docker run -p 9324:9324 softwaremill/elasticmq-native` and
+    /// `SQS_TEST_ENDPOINT=http://localhost:9324 cargo test -- --ignored` (or LocalStack on port 4566)
+    #[tokio::test]
+    #[ignore = "requires a local SQS at SQS_TEST_ENDPOINT"]
+    async fn receives_and_deletes_messages() {
+        let endpoint =
+            std::env::var("SQS_TEST_ENDPOINT").unwrap_or("http://localhost:9324".to_owned());
+
+        // local SQS servers accept any credentials, but requests must still be signed
+        for (key, value) in [
+            ("AWS_ACCESS_KEY_ID", "test"),
+            ("AWS_SECRET_ACCESS_KEY", "test"),
+        ] {
+            if std::env::var(key).is_err() {
+                std::env::set_var(key, value);
+            }
+        }
+
+        let cfg: config::AppConfig =
+            envy::from_iter([("AWS_SQS_ENDPOINT".to_owned(), endpoint)]).unwrap();
+
+        let client = SqsClient::new(&cfg).await;
+
+        let queue_url = client
+            .aws_client
+            .create_queue()
+            .queue_name(format!("mailer-test-{}", uuid::Uuid::new_v4().simple()))
+            .send()
+            .await
+            .unwrap()
+            .queue_url()
+            .unwrap()
+            .to_owned();
+
+        client
+            .aws_client
+            .send_message()
+            .queue_url(&queue_url)
+            .message_body(r#"{"Type":"Notification"}"#)
+            .send()
+            .await
+            .unwrap();
+
+        let messages = client.receive_messages(&queue_url, 10, 1).await.unwrap();
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].body, r#"{"Type":"Notification"}"#);
+
+        client
+            .delete_message(&queue_url, &messages[0].receipt_handle)
+            .await
+            .unwrap();
+
+        // deleted messages are not received again once the visibility timeout expires
+        client
+            .aws_client
+            .change_message_visibility()
+            .queue_url(&queue_url)
+            .receipt_handle(&messages[0].receipt_handle)
+            .visibility_timeout(0)
+            .send()
+            .await
+            .ok();
+
+        assert!(client
+            .receive_messages(&queue_url, 10, 1)
+            .await
+            .unwrap()
+            .is_empty());
+
+        client
+            .aws_client
+            .delete_queue()
+            .queue_url(&queue_url)
+            .send()
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+use super::client::{SqsClient, SqsMessage};
+use crate::{
+    controller::dto::ses::SnsNotification,
+    ses::handler::{self, SesEventHandler},
+};
+use std::{sync::Arc, time::Duration};
+use tracing::error;
+
+/// see: https://docs.aws.amazon.com/AWSSimpleQueueService/latest/APIReference/API_ReceiveMessage.html
+static MAX_MESSAGES_PER_RECEIVE: u8 = 10;
+
+static RECEIVE_ERROR_RETRY_INTERVAL_SECS: u64 = 5;
+
+/// Consumes SNS notifications wrapping SES events from a SQS queue, as an alternative to the `/ses-events` HTTP endpoint
+#[derive(Debug)]
+pub struct SqsPoller {
+    client: SqsClient,
+    queue_url: String,
+    wait_time_secs: u8,
+    ses_event_handler: Arc<SesEventHandler>,
+}
+
+impl SqsPoller {
+    pub fn new(
+        client: SqsClient,
+        queue_url: String,
+        wait_time_secs: u8,
+        ses_event_handler: Arc<SesEventHandler>,
+    ) -> SqsPoller {
+        SqsPoller {
+            client,
+            queue_url,
+            wait_time_secs,
+            ses_event_handler,
+        }
+    }
+
+    pub async fn start(&self) {
+        println!("[SQS] polling SES events from {}", self.queue_url);
+
+        loop {
+            let messages = self
+                .client
+                .receive_messages(
+                    &self.queue_url,
+                    MAX_MESSAGES_PER_RECEIVE,
+                    self.wait_time_secs,
+                )
+                .await;
+
+            match messages {
+                Ok(messages) => {
+                    for message in messages {
+                        self.handle_message(message).await;
+                    }
+                }
+                Err(e) => {
+                    println!("[SQS] failed to receive messages: {}", e);
+                    tokio::time::sleep(Duration::from_secs(RECEIVE_ERROR_RETRY_INTERVAL_SECS))
+                        .await;
+                }
+            }
+        }
+    }
+
+    /// messages are deleted only after their event is published, so a failed publish is retried once
+    /// the message visibility timeout expires, messages that are not valid SES events are deleted
+    async fn handle_message(&self, message: SqsMessage) {
+        let email_event = serde_json::from_str::<SnsNotification>(&message.body)
+            .map_err(|e| format!("failed to parse SQS message to SnsNotification: {}", e))
+            .and_then(|sns_notification| {
+                let sns_message_id = sns_notification.message_id.clone();
+                handler::get_email_event_from_sns_notification(sns_notification)
+                    .map(|email_event| (email_event, sns_message_id))
+            });
+
+        match email_event {
+            Ok((email_event, sns_message_id)) => {
+                if let Err(e) = self
+                    .ses_event_handler
+                    .handle(email_event, Some(&sns_message_id))
+                    .await
+                {
+                    error!(
+                        "ses event publishing failed, SQS message will be retried: {}",
+                        e
+                    );
+                    return;
+                }
+            }
+            Err(e) => error!("discarding SQS message {}: {}", message.message_id, e),
+        }
+
+        if let Err(e) = self
+            .client
+            .delete_message(&self.queue_url, &message.receipt_handle)
+            .await
+        {
+            error!("failed to delete SQS message {}: {}", message.message_id, e)
+        }
+    }
+}
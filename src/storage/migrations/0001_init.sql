@@ -0,0 +1,36 @@
+CREATE TABLE requests (
+    uuid TEXT PRIMARY KEY NOT NULL,
+    state TEXT NOT NULL,
+    -- JSON of the SendEmailIn that originated the request
+    request TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX requests_created_at_idx ON requests (created_at);
+
+CREATE TABLE recipients (
+    request_uuid TEXT NOT NULL REFERENCES requests (uuid) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    state TEXT NOT NULL,
+    -- see: RecipientState::precedence
+    state_precedence INTEGER NOT NULL,
+    ses_message_id TEXT,
+    error TEXT,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (request_uuid, email)
+);
+
+CREATE TABLE events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    request_uuid TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    ses_message_id TEXT NOT NULL,
+    -- JSON of the published EmailEvent
+    payload TEXT NOT NULL,
+    received_at TEXT NOT NULL
+);
+
+CREATE INDEX events_request_uuid_idx ON events (request_uuid);
+
+CREATE INDEX events_received_at_idx ON events (received_at);
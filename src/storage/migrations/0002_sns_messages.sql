@@ -0,0 +1,7 @@
+-- ids of the received SNS messages, used to drop messages delivered more than once
+CREATE TABLE sns_messages (
+    message_id TEXT PRIMARY KEY NOT NULL,
+    received_at TEXT NOT NULL
+);
+
+CREATE INDEX sns_messages_received_at_idx ON sns_messages (received_at);
@@ -0,0 +1,8 @@
+-- addresses that must not receive emails, not affected by the retention policy
+CREATE TABLE suppressions (
+    -- trimmed and lowercased email address
+    email TEXT PRIMARY KEY NOT NULL,
+    reason TEXT NOT NULL,
+    detail TEXT,
+    created_at TEXT NOT NULL
+);
@@ -0,0 +1,13 @@
+-- resends of recipients that bounced transiently, see: ResendPolicy
+CREATE TABLE resends (
+    request_uuid TEXT NOT NULL REFERENCES requests (uuid) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    -- amount of resends scheduled for the recipient
+    attempts INTEGER NOT NULL,
+    -- NULL once the resend was taken to be sent
+    due_at TEXT,
+    last_bounce TEXT,
+    PRIMARY KEY (request_uuid, email)
+);
+
+CREATE INDEX resends_due_at_idx ON resends (due_at);
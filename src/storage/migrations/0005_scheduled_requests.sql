@@ -0,0 +1,5 @@
+ALTER TABLE requests ADD COLUMN send_at TEXT;
+
+ALTER TABLE requests ADD COLUMN expires_at TEXT;
+
+CREATE INDEX requests_state_send_at_idx ON requests (state, send_at);
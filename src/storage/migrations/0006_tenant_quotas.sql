@@ -0,0 +1,10 @@
+-- emails of each request reserved on the daily quota of its tenant, only the last 24 hours are kept
+CREATE TABLE tenant_quota_reservations (
+    tenant TEXT NOT NULL,
+    request_uuid TEXT NOT NULL,
+    emails INTEGER NOT NULL,
+    reserved_at TEXT NOT NULL,
+    PRIMARY KEY (tenant, request_uuid)
+);
+
+CREATE INDEX tenant_quota_reservations_reserved_at_idx ON tenant_quota_reservations (tenant, reserved_at);
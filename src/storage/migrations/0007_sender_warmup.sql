@@ -0,0 +1,10 @@
+-- emails of each request counted on the warm-up cap of its sender identity, per UTC day
+CREATE TABLE warmup_reservations (
+    identity TEXT NOT NULL,
+    request_uuid TEXT NOT NULL,
+    day TEXT NOT NULL,
+    emails INTEGER NOT NULL,
+    PRIMARY KEY (identity, request_uuid)
+);
+
+CREATE INDEX warmup_reservations_day_idx ON warmup_reservations (identity, day);
@@ -0,0 +1,187 @@
+use crate::controller::dto::{
+    events::EmailEvent,
+    input::SendEmailIn,
+    status::{RecipientState, RequestState, RequestStatus},
+    suppression::SuppressedAddress,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::{fmt::Debug, sync::Arc};
+use tokio::time;
+use tracing::error;
+use uuid::Uuid;
+
+/// interval between executions of the retention policy
+static RETENTION_POLICY_INTERVAL_SECS: u64 = 60 * 60;
+
+/// a resend of a recipient of a request whose due date has been reached
+#[derive(Debug, Clone)]
+pub struct ScheduledResend {
+    pub request_uuid: Uuid,
+    pub email: String,
+    pub attempt: u32,
+}
+
+/// result of reserving the emails of a request on the daily quota of its tenant
+#[derive(Debug, Clone, Copy)]
+pub struct TenantQuotaReservation {
+    /// if the emails were reserved, false if they would exceed the quota
+    pub reserved: bool,
+
+    /// emails reserved by the tenant in the last 24 hours, without the emails of this request
+    pub used: u64,
+}
+
+/// result of reserving the emails of a request on the warm-up cap of its sender identity for a day
+#[derive(Debug, Clone, Copy)]
+pub struct WarmupReservation {
+    /// if the emails were reserved, false if they would exceed the cap
+    pub reserved: bool,
+
+    /// emails reserved by the identity on the day, without the emails of this request
+    pub used: u64,
+}
+
+/// Persistence for email requests, the state of their recipients and the SES events they received.
+///
+/// Implementations must be safe to share between the mailer, the AMQP router and the HTTP server,
+/// to plug another database implement this trait and pass it where `SqliteRepository` is created
+#[async_trait]
+pub trait Repository: Send + Sync + Debug {
+    /// stores a request and its recipients, saving a request that already exists updates its state and
+    /// request (eg: the new `send_at` of a deferred request) but keeps its recipients
+    async fn save_request(
+        &self,
+        uuid: Uuid,
+        request: &SendEmailIn,
+        state: RequestState,
+    ) -> Result<(), String>;
+
+    async fn get_request(&self, uuid: Uuid) -> Result<Option<SendEmailIn>, String>;
+
+    /// returns the scheduled requests whose `sendAt` is before `now`, they are returned again
+    /// until they are marked as enqueued
+    async fn get_due_scheduled_requests(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(Uuid, SendEmailIn)>, String>;
+
+    /// sets a due scheduled request to the received state once enqueued, so it is not returned again, a
+    /// request that is no longer scheduled or was scheduled again after `now` (eg: deferred) is not changed
+    async fn mark_scheduled_request_enqueued(
+        &self,
+        uuid: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<(), String>;
+
+    /// sets the request state to cancelled if its scheduled, returning false if its not
+    async fn cancel_scheduled_request(&self, uuid: Uuid) -> Result<bool, String>;
+
+    async fn set_request_state(&self, uuid: Uuid, state: RequestState) -> Result<(), String>;
+
+    /// updates the state of the given recipients of a request, recipients that are not part
+    /// of the request and updates to a state of lower precedence than the current are ignored
+    async fn set_recipients_state(
+        &self,
+        uuid: Uuid,
+        emails: &[String],
+        state: RecipientState,
+        ses_message_id: Option<String>,
+        error: Option<String>,
+    ) -> Result<(), String>;
+
+    /// sets the state of a recipient back to received regardless of its current state, so it can be sent again
+    async fn reset_recipient_state(&self, uuid: Uuid, email: &str) -> Result<(), String>;
+
+    async fn save_event(&self, event: &EmailEvent) -> Result<(), String>;
+
+    async fn get_request_status(&self, uuid: Uuid) -> Result<Option<RequestStatus>, String>;
+
+    /// records the id of a received SNS message, returning false if the id was already recorded after `not_before`
+    async fn register_sns_message(
+        &self,
+        message_id: &str,
+        not_before: DateTime<Utc>,
+    ) -> Result<bool, String>;
+
+    async fn delete_sns_message(&self, message_id: &str) -> Result<(), String>;
+
+    /// adds the address to the suppression list, replacing the reason and detail if its already suppressed
+    async fn add_suppression(&self, suppression: &SuppressedAddress) -> Result<(), String>;
+
+    /// removes the address from the suppression list, returning false if it was not suppressed
+    async fn remove_suppression(&self, email: &str) -> Result<bool, String>;
+
+    async fn list_suppressions(&self) -> Result<Vec<SuppressedAddress>, String>;
+
+    /// returns which of the given addresses are suppressed, addresses are compared case insensitively
+    async fn filter_suppressed(&self, emails: &[String]) -> Result<Vec<String>, String>;
+
+    /// amount of resends already scheduled for the recipient
+    async fn get_resend_attempts(&self, uuid: Uuid, email: &str) -> Result<u32, String>;
+
+    /// schedules the resend `attempt` of the recipient to `due_at`, replacing any previous schedule, the last
+    /// bounce is kept if `last_bounce` is None
+    async fn schedule_resend(
+        &self,
+        uuid: Uuid,
+        email: &str,
+        attempt: u32,
+        due_at: DateTime<Utc>,
+        last_bounce: Option<String>,
+    ) -> Result<(), String>;
+
+    /// returns the resends due before `now`, marking them as taken so they are returned only once
+    async fn take_due_resends(&self, now: DateTime<Utc>) -> Result<Vec<ScheduledResend>, String>;
+
+    /// reserves the emails of the request on the tenant daily quota unless they would exceed it, reserving
+    /// a request that was already reserved (eg: a redelivered request) does not count its emails again
+    async fn reserve_tenant_quota(
+        &self,
+        tenant: &str,
+        request_uuid: Uuid,
+        emails: u64,
+        daily_quota: u64,
+    ) -> Result<TenantQuotaReservation, String>;
+
+    /// deletes the reservation of the request on the tenant daily quota, eg: when the request is deferred
+    async fn release_tenant_quota(&self, tenant: &str, request_uuid: Uuid) -> Result<(), String>;
+
+    /// reserves the emails of the request on the warm-up cap of the sender identity for `day` unless they would
+    /// exceed it, reserving a request that was already reserved does not count its emails again
+    async fn reserve_warmup_emails(
+        &self,
+        identity: &str,
+        request_uuid: Uuid,
+        emails: u64,
+        day: NaiveDate,
+        daily_cap: u64,
+    ) -> Result<WarmupReservation, String>;
+
+    /// emails reserved by the sender identity on `day`
+    async fn get_warmup_usage(&self, identity: &str, day: NaiveDate) -> Result<u64, String>;
+
+    /// deletes requests (and their recipients and resends), events, SNS message ids and quota and warm-up reservations created
+    /// before `date`, returning the amount of deleted rows, scheduled requests are kept until they are sent
+    async fn delete_older_than(&self, date: DateTime<Utc>) -> Result<usize, String>;
+}
+
+/// periodically deletes all data older than `retention_days`, a retention of 0 days keeps data forever
+pub async fn run_retention_policy(repository: Arc<dyn Repository>, retention_days: u32) {
+    if retention_days == 0 {
+        return;
+    }
+
+    let mut interval = time::interval(time::Duration::from_secs(RETENTION_POLICY_INTERVAL_SECS));
+
+    loop {
+        interval.tick().await;
+
+        let cutoff = Utc::now() - Duration::days(retention_days.into());
+
+        match repository.delete_older_than(cutoff).await {
+            Ok(deleted) => println!("[DB] retention policy deleted {} rows", deleted),
+            Err(e) => error!("failed to apply retention policy: {}", e),
+        }
+    }
+}
@@ -0,0 +1,1107 @@
+use super::repository::{Repository, ScheduledResend, TenantQuotaReservation, WarmupReservation};
+use crate::controller::dto::{
+    events::EmailEvent,
+    input::SendEmailIn,
+    status::{RecipientState, RecipientStatus, RequestState, RequestStatus},
+    suppression::{normalize_email, SuppressedAddress, SuppressionReason},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use uuid::Uuid;
+
+/// migrations applied in order, the index of the last applied migration is stored in the `user_version` pragma
+static MIGRATIONS: &[&str] = &[
+    include_str!("migrations/0001_init.sql"),
+    include_str!("migrations/0002_sns_messages.sql"),
+    include_str!("migrations/0003_suppressions.sql"),
+    include_str!("migrations/0004_resends.sql"),
+    include_str!("migrations/0005_scheduled_requests.sql"),
+    include_str!("migrations/0006_tenant_quotas.sql"),
+    include_str!("migrations/0007_sender_warmup.sql"),
+];
+
+#[derive(Debug, Clone)]
+pub struct SqliteRepository {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+
+        println!("[DB] applied migration {}", i + 1);
+    }
+
+    Ok(())
+}
+
+impl SqliteRepository {
+    /// opens (or creates) the database at `path` and applies all pending migrations
+    pub fn new(path: &str, pool_size: u32) -> Result<SqliteRepository, String> {
+        let repository = Self::open(SqliteConnectionManager::file(path), pool_size)?;
+
+        println!("[DB] sqlite database ready at {}", path);
+
+        Ok(repository)
+    }
+
+    /// a database in memory, with a single connection since each connection to a in-memory database
+    /// opens a database of its own
+    #[cfg(test)]
+    pub fn in_memory() -> SqliteRepository {
+        Self::open(SqliteConnectionManager::memory(), 1).unwrap()
+    }
+
+    fn open(manager: SqliteConnectionManager, pool_size: u32) -> Result<SqliteRepository, String> {
+        let manager = manager.with_init(|conn| {
+            conn.execute_batch(
+                "PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL; PRAGMA busy_timeout = 5000;",
+            )
+        });
+
+        let pool = Pool::builder()
+            .max_size(pool_size)
+            .build(manager)
+            .map_err(|e| format!("failed to create sqlite pool: {}", e))?;
+
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        migrate(&mut conn).map_err(|e| format!("failed to apply migrations: {}", e))?;
+
+        Ok(SqliteRepository { pool })
+    }
+
+    /// runs `f` with a pooled connection on the blocking thread pool, since rusqlite is synchronous
+    async fn with_conn<T, F>(&self, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let pool = self.pool.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get().map_err(|e| e.to_string())?;
+            f(&mut conn).map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+}
+
+#[async_trait]
+impl Repository for SqliteRepository {
+    async fn save_request(
+        &self,
+        uuid: Uuid,
+        request: &SendEmailIn,
+        state: RequestState,
+    ) -> Result<(), String> {
+        let request_json = serde_json::to_string(request).map_err(|e| e.to_string())?;
+        let emails: Vec<String> = request.to.iter().map(|r| r.email.clone()).collect();
+        let (send_at, expires_at) = (request.send_at, request.expires_at);
+
+        self.with_conn(move |conn| {
+            let now = Utc::now();
+            let tx = conn.transaction()?;
+
+            tx.execute(
+                "INSERT INTO requests (uuid, state, request, created_at, updated_at, send_at, expires_at)
+                VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6)
+                ON CONFLICT (uuid) DO UPDATE SET state = excluded.state, request = excluded.request,
+                updated_at = excluded.updated_at, send_at = excluded.send_at, expires_at = excluded.expires_at",
+                params![uuid.to_string(), state.to_string(), request_json, now, send_at, expires_at],
+            )?;
+
+            for email in emails {
+                tx.execute(
+                    "INSERT OR IGNORE INTO recipients (request_uuid, email, state, state_precedence, updated_at)
+                    VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        uuid.to_string(),
+                        email,
+                        RecipientState::Received.to_string(),
+                        RecipientState::Received.precedence(),
+                        now
+                    ],
+                )?;
+            }
+
+            tx.commit()
+        })
+        .await
+    }
+
+    async fn get_request(&self, uuid: Uuid) -> Result<Option<SendEmailIn>, String> {
+        let request_json = self
+            .with_conn(move |conn| {
+                conn.query_row(
+                    "SELECT request FROM requests WHERE uuid = ?1",
+                    params![uuid.to_string()],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()
+            })
+            .await?;
+
+        request_json
+            .map(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    async fn get_due_scheduled_requests(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(Uuid, SendEmailIn)>, String> {
+        let rows = self
+            .with_conn(move |conn| {
+                conn.prepare(
+                    "SELECT uuid, request FROM requests
+                    WHERE state = ?1 AND send_at <= ?2 ORDER BY send_at",
+                )?
+                .query_map(params![RequestState::Scheduled.to_string(), now], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .await?;
+
+        rows.into_iter()
+            .map(|(uuid, request_json)| {
+                Ok((
+                    uuid.parse().map_err(|e: uuid::Error| e.to_string())?,
+                    serde_json::from_str(&request_json).map_err(|e| e.to_string())?,
+                ))
+            })
+            .collect()
+    }
+
+    async fn mark_scheduled_request_enqueued(
+        &self,
+        uuid: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<(), String> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE requests SET state = ?1, updated_at = ?2
+                WHERE uuid = ?3 AND state = ?4 AND send_at <= ?2",
+                params![
+                    RequestState::Received.to_string(),
+                    now,
+                    uuid.to_string(),
+                    RequestState::Scheduled.to_string()
+                ],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn cancel_scheduled_request(&self, uuid: Uuid) -> Result<bool, String> {
+        self.with_conn(move |conn| {
+            let updated = conn.execute(
+                "UPDATE requests SET state = ?1, updated_at = ?2 WHERE uuid = ?3 AND state = ?4",
+                params![
+                    RequestState::Cancelled.to_string(),
+                    Utc::now(),
+                    uuid.to_string(),
+                    RequestState::Scheduled.to_string()
+                ],
+            )?;
+
+            Ok(updated > 0)
+        })
+        .await
+    }
+
+    async fn set_request_state(&self, uuid: Uuid, state: RequestState) -> Result<(), String> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE requests SET state = ?1, updated_at = ?2 WHERE uuid = ?3",
+                params![state.to_string(), Utc::now(), uuid.to_string()],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_recipients_state(
+        &self,
+        uuid: Uuid,
+        emails: &[String],
+        state: RecipientState,
+        ses_message_id: Option<String>,
+        error: Option<String>,
+    ) -> Result<(), String> {
+        let emails = emails.to_vec();
+
+        self.with_conn(move |conn| {
+            let now = Utc::now();
+            let tx = conn.transaction()?;
+
+            for email in emails {
+                tx.execute(
+                    "UPDATE recipients SET
+                        state = ?1,
+                        state_precedence = ?2,
+                        ses_message_id = COALESCE(?3, ses_message_id),
+                        error = COALESCE(?4, error),
+                        updated_at = ?5
+                    WHERE request_uuid = ?6 AND email = ?7 AND state_precedence <= ?2",
+                    params![
+                        state.to_string(),
+                        state.precedence(),
+                        ses_message_id,
+                        error,
+                        now,
+                        uuid.to_string(),
+                        email
+                    ],
+                )?;
+            }
+
+            tx.execute(
+                "UPDATE requests SET updated_at = ?1 WHERE uuid = ?2",
+                params![now, uuid.to_string()],
+            )?;
+
+            tx.commit()
+        })
+        .await
+    }
+
+    async fn reset_recipient_state(&self, uuid: Uuid, email: &str) -> Result<(), String> {
+        let email = email.to_owned();
+
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE recipients SET state = ?1, state_precedence = ?2, updated_at = ?3
+                WHERE request_uuid = ?4 AND email = ?5",
+                params![
+                    RecipientState::Received.to_string(),
+                    RecipientState::Received.precedence(),
+                    Utc::now(),
+                    uuid.to_string(),
+                    email
+                ],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn save_event(&self, event: &EmailEvent) -> Result<(), String> {
+        let payload = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        let request_uuid = event.request_uuid.clone();
+        let event_type = event.event_type.clone();
+        let ses_message_id = event.mail.message_id.clone();
+
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO events (request_uuid, event_type, ses_message_id, payload, received_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![request_uuid, event_type, ses_message_id, payload, Utc::now()],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_request_status(&self, uuid: Uuid) -> Result<Option<RequestStatus>, String> {
+        self.with_conn(move |conn| {
+            let request = conn
+                .query_row(
+                    "SELECT state, created_at, updated_at FROM requests WHERE uuid = ?1",
+                    params![uuid.to_string()],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, DateTime<Utc>>(1)?,
+                            row.get::<_, DateTime<Utc>>(2)?,
+                        ))
+                    },
+                )
+                .optional()?;
+
+            let Some((state, created_at, updated_at)) = request else {
+                return Ok(None);
+            };
+
+            let mut stmt = conn.prepare(
+                "SELECT email, state, ses_message_id, error, updated_at
+                FROM recipients WHERE request_uuid = ?1 ORDER BY rowid",
+            )?;
+
+            let recipients = stmt
+                .query_map(params![uuid.to_string()], |row| {
+                    Ok(RecipientStatus {
+                        email: row.get(0)?,
+                        state: row
+                            .get::<_, String>(1)?
+                            .parse()
+                            .unwrap_or(RecipientState::Received),
+                        ses_message_id: row.get(2)?,
+                        error: row.get(3)?,
+                        updated_at: row.get(4)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            Ok(Some(RequestStatus {
+                request_uuid: uuid,
+                state: state.parse().unwrap_or(RequestState::Received),
+                created_at,
+                updated_at,
+                recipients,
+            }))
+        })
+        .await
+    }
+
+    async fn register_sns_message(
+        &self,
+        message_id: &str,
+        not_before: DateTime<Utc>,
+    ) -> Result<bool, String> {
+        let message_id = message_id.to_owned();
+
+        self.with_conn(move |conn| {
+            // a id recorded before `not_before` is expired, so it is recorded again as new
+            let changed = conn.execute(
+                "INSERT INTO sns_messages (message_id, received_at) VALUES (?1, ?2)
+                ON CONFLICT (message_id) DO UPDATE SET received_at = excluded.received_at
+                WHERE received_at < ?3",
+                params![message_id, Utc::now(), not_before],
+            )?;
+
+            Ok(changed > 0)
+        })
+        .await
+    }
+
+    async fn delete_sns_message(&self, message_id: &str) -> Result<(), String> {
+        let message_id = message_id.to_owned();
+
+        self.with_conn(move |conn| {
+            conn.execute(
+                "DELETE FROM sns_messages WHERE message_id = ?1",
+                params![message_id],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn add_suppression(&self, suppression: &SuppressedAddress) -> Result<(), String> {
+        let suppression = suppression.clone();
+
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO suppressions (email, reason, detail, created_at) VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (email) DO UPDATE SET reason = excluded.reason, detail = excluded.detail",
+                params![
+                    normalize_email(&suppression.email),
+                    suppression.reason.to_string(),
+                    suppression.detail,
+                    suppression.created_at
+                ],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_suppression(&self, email: &str) -> Result<bool, String> {
+        let email = normalize_email(email);
+
+        self.with_conn(move |conn| {
+            let deleted =
+                conn.execute("DELETE FROM suppressions WHERE email = ?1", params![email])?;
+
+            Ok(deleted > 0)
+        })
+        .await
+    }
+
+    async fn list_suppressions(&self) -> Result<Vec<SuppressedAddress>, String> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT email, reason, detail, created_at FROM suppressions ORDER BY created_at",
+            )?;
+
+            let suppressions = stmt
+                .query_map([], |row| {
+                    Ok(SuppressedAddress {
+                        email: row.get(0)?,
+                        reason: row
+                            .get::<_, String>(1)?
+                            .parse()
+                            .unwrap_or(SuppressionReason::Manual),
+                        detail: row.get(2)?,
+                        created_at: row.get(3)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            Ok(suppressions)
+        })
+        .await
+    }
+
+    async fn filter_suppressed(&self, emails: &[String]) -> Result<Vec<String>, String> {
+        let emails = emails.to_vec();
+
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare_cached("SELECT 1 FROM suppressions WHERE email = ?1")?;
+            let mut suppressed = vec![];
+
+            for email in emails {
+                if stmt.exists(params![normalize_email(&email)])? {
+                    suppressed.push(email);
+                }
+            }
+
+            Ok(suppressed)
+        })
+        .await
+    }
+
+    async fn get_resend_attempts(&self, uuid: Uuid, email: &str) -> Result<u32, String> {
+        let email = email.to_owned();
+
+        self.with_conn(move |conn| {
+            let attempts = conn
+                .query_row(
+                    "SELECT attempts FROM resends WHERE request_uuid = ?1 AND email = ?2",
+                    params![uuid.to_string(), email],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            Ok(attempts.unwrap_or(0))
+        })
+        .await
+    }
+
+    async fn schedule_resend(
+        &self,
+        uuid: Uuid,
+        email: &str,
+        attempt: u32,
+        due_at: DateTime<Utc>,
+        last_bounce: Option<String>,
+    ) -> Result<(), String> {
+        let email = email.to_owned();
+
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO resends (request_uuid, email, attempts, due_at, last_bounce) VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (request_uuid, email) DO UPDATE SET
+                    attempts = excluded.attempts,
+                    due_at = excluded.due_at,
+                    last_bounce = COALESCE(excluded.last_bounce, resends.last_bounce)",
+                params![uuid.to_string(), email, attempt, due_at, last_bounce],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn take_due_resends(&self, now: DateTime<Utc>) -> Result<Vec<ScheduledResend>, String> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+
+            let resends = {
+                let mut stmt = tx.prepare(
+                    "SELECT request_uuid, email, attempts FROM resends
+                    WHERE due_at IS NOT NULL AND due_at <= ?1 ORDER BY due_at",
+                )?;
+
+                let rows = stmt
+                    .query_map(params![now], |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, u32>(2)?,
+                        ))
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+
+                rows.into_iter()
+                    .filter_map(|(uuid, email, attempt)| {
+                        Some(ScheduledResend {
+                            request_uuid: uuid.parse().ok()?,
+                            email,
+                            attempt,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            tx.execute(
+                "UPDATE resends SET due_at = NULL WHERE due_at IS NOT NULL AND due_at <= ?1",
+                params![now],
+            )?;
+
+            tx.commit()?;
+
+            Ok(resends)
+        })
+        .await
+    }
+
+    async fn reserve_tenant_quota(
+        &self,
+        tenant: &str,
+        request_uuid: Uuid,
+        emails: u64,
+        daily_quota: u64,
+    ) -> Result<TenantQuotaReservation, String> {
+        let tenant = tenant.to_owned();
+
+        self.with_conn(move |conn| {
+            let now = Utc::now();
+            let since = now - chrono::Duration::hours(24);
+
+            // the usage is read and reserved in a write transaction, so concurrent requests cannot exceed the quota
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+            tx.execute(
+                "DELETE FROM tenant_quota_reservations WHERE tenant = ?1 AND reserved_at < ?2",
+                params![tenant, since],
+            )?;
+
+            let already_reserved = tx
+                .query_row(
+                    "SELECT 1 FROM tenant_quota_reservations WHERE tenant = ?1 AND request_uuid = ?2",
+                    params![tenant, request_uuid.to_string()],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+
+            let used: u64 = tx.query_row(
+                "SELECT COALESCE(SUM(emails), 0) FROM tenant_quota_reservations
+                WHERE tenant = ?1 AND request_uuid != ?2",
+                params![tenant, request_uuid.to_string()],
+                |row| row.get(0),
+            )?;
+
+            let reserved = already_reserved || used + emails <= daily_quota;
+
+            if reserved && !already_reserved {
+                tx.execute(
+                    "INSERT INTO tenant_quota_reservations (tenant, request_uuid, emails, reserved_at)
+                    VALUES (?1, ?2, ?3, ?4)",
+                    params![tenant, request_uuid.to_string(), emails, now],
+                )?;
+            }
+
+            tx.commit()?;
+
+            Ok(TenantQuotaReservation { reserved, used })
+        })
+        .await
+    }
+
+    async fn release_tenant_quota(&self, tenant: &str, request_uuid: Uuid) -> Result<(), String> {
+        let tenant = tenant.to_owned();
+
+        self.with_conn(move |conn| {
+            conn.execute(
+                "DELETE FROM tenant_quota_reservations WHERE tenant = ?1 AND request_uuid = ?2",
+                params![tenant, request_uuid.to_string()],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn reserve_warmup_emails(
+        &self,
+        identity: &str,
+        request_uuid: Uuid,
+        emails: u64,
+        day: NaiveDate,
+        daily_cap: u64,
+    ) -> Result<WarmupReservation, String> {
+        let identity = identity.to_owned();
+
+        self.with_conn(move |conn| {
+            // the usage is read and reserved in a write transaction, so concurrent requests cannot exceed the cap
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+            tx.execute(
+                "DELETE FROM warmup_reservations WHERE identity = ?1 AND day < ?2",
+                params![identity, day],
+            )?;
+
+            let already_reserved = tx
+                .query_row(
+                    "SELECT 1 FROM warmup_reservations WHERE identity = ?1 AND request_uuid = ?2",
+                    params![identity, request_uuid.to_string()],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+
+            let used: u64 = tx.query_row(
+                "SELECT COALESCE(SUM(emails), 0) FROM warmup_reservations
+                WHERE identity = ?1 AND day = ?2 AND request_uuid != ?3",
+                params![identity, day, request_uuid.to_string()],
+                |row| row.get(0),
+            )?;
+
+            let reserved = already_reserved || used + emails <= daily_cap;
+
+            if reserved && !already_reserved {
+                tx.execute(
+                    "INSERT INTO warmup_reservations (identity, request_uuid, day, emails)
+                    VALUES (?1, ?2, ?3, ?4)",
+                    params![identity, request_uuid.to_string(), day, emails],
+                )?;
+            }
+
+            tx.commit()?;
+
+            Ok(WarmupReservation { reserved, used })
+        })
+        .await
+    }
+
+    async fn get_warmup_usage(&self, identity: &str, day: NaiveDate) -> Result<u64, String> {
+        let identity = identity.to_owned();
+
+        self.with_conn(move |conn| {
+            conn.query_row(
+                "SELECT COALESCE(SUM(emails), 0) FROM warmup_reservations
+                WHERE identity = ?1 AND day = ?2",
+                params![identity, day],
+                |row| row.get(0),
+            )
+        })
+        .await
+    }
+
+    async fn delete_older_than(&self, date: DateTime<Utc>) -> Result<usize, String> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+
+            // deleted before their requests, the foreign key would cascade them but they would not be counted
+            let deleted_resends = tx.execute(
+                "DELETE FROM resends WHERE request_uuid IN
+                (SELECT uuid FROM requests WHERE created_at < ?1 AND state != ?2)",
+                params![date, RequestState::Scheduled.to_string()],
+            )?;
+
+            let deleted_requests = tx.execute(
+                "DELETE FROM requests WHERE created_at < ?1 AND state != ?2",
+                params![date, RequestState::Scheduled.to_string()],
+            )?;
+
+            let deleted_events =
+                tx.execute("DELETE FROM events WHERE received_at < ?1", params![date])?;
+
+            let deleted_sns_messages = tx.execute(
+                "DELETE FROM sns_messages WHERE received_at < ?1",
+                params![date],
+            )?;
+
+            // reservations are pruned when reserving for the same tenant or identity, these are the ones of
+            // tenants and identities that stopped sending
+            let deleted_quota_reservations = tx.execute(
+                "DELETE FROM tenant_quota_reservations WHERE reserved_at < ?1",
+                params![date],
+            )?;
+
+            let deleted_warmup_reservations = tx.execute(
+                "DELETE FROM warmup_reservations WHERE day < ?1",
+                params![date.date_naive()],
+            )?;
+
+            tx.commit()?;
+
+            Ok(deleted_resends
+                + deleted_requests
+                + deleted_events
+                + deleted_sns_messages
+                + deleted_quota_reservations
+                + deleted_warmup_reservations)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::json;
+
+    fn repository() -> SqliteRepository {
+        SqliteRepository::in_memory()
+    }
+
+    fn request(emails: &[&str], send_at: Option<DateTime<Utc>>) -> SendEmailIn {
+        let to: Vec<_> = emails
+            .iter()
+            .map(|email| json!({ "email": email }))
+            .collect();
+
+        serde_json::from_value(json!({
+            "to": to,
+            "subject": "hi",
+            "bodyText": "hi",
+            "sendAt": send_at,
+        }))
+        .unwrap()
+    }
+
+    fn count(repository: &SqliteRepository, table: &str) -> usize {
+        repository
+            .pool
+            .get()
+            .unwrap()
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn migrations_are_applied_once() {
+        let repository = repository();
+        let mut conn = repository.pool.get().unwrap();
+
+        let version = |conn: &Connection| -> usize {
+            conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+                .unwrap()
+        };
+
+        assert_eq!(version(&conn), MIGRATIONS.len());
+
+        migrate(&mut conn).unwrap();
+        assert_eq!(version(&conn), MIGRATIONS.len());
+    }
+
+    #[tokio::test]
+    async fn tenant_quota_counts_each_request_once() {
+        let repository = repository();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let reservation = repository
+            .reserve_tenant_quota("acme", first, 3, 5)
+            .await
+            .unwrap();
+        assert!(reservation.reserved);
+        assert_eq!(reservation.used, 0);
+
+        // a redelivered request is reserved again without counting its emails twice
+        let reservation = repository
+            .reserve_tenant_quota("acme", first, 3, 5)
+            .await
+            .unwrap();
+        assert!(reservation.reserved);
+        assert_eq!(reservation.used, 0);
+
+        let reservation = repository
+            .reserve_tenant_quota("acme", second, 3, 5)
+            .await
+            .unwrap();
+        assert!(!reservation.reserved);
+        assert_eq!(reservation.used, 3);
+
+        let reservation = repository
+            .reserve_tenant_quota("other", second, 3, 5)
+            .await
+            .unwrap();
+        assert!(reservation.reserved);
+        assert_eq!(reservation.used, 0);
+    }
+
+    #[tokio::test]
+    async fn warmup_cap_counts_each_request_once_per_day() {
+        let repository = repository();
+        let today = Utc::now().date_naive();
+        let tomorrow = today + Duration::days(1);
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+
+        for _ in 0..2 {
+            let reservation = repository
+                .reserve_warmup_emails("example.com", first, 3, today, 5)
+                .await
+                .unwrap();
+            assert!(reservation.reserved);
+            assert_eq!(reservation.used, 0);
+        }
+
+        let reservation = repository
+            .reserve_warmup_emails("example.com", second, 3, today, 5)
+            .await
+            .unwrap();
+        assert!(!reservation.reserved);
+        assert_eq!(reservation.used, 3);
+
+        assert_eq!(
+            repository
+                .get_warmup_usage("example.com", today)
+                .await
+                .unwrap(),
+            3
+        );
+
+        let reservation = repository
+            .reserve_warmup_emails("example.com", second, 3, tomorrow, 5)
+            .await
+            .unwrap();
+        assert!(reservation.reserved);
+        assert_eq!(reservation.used, 0);
+    }
+
+    #[tokio::test]
+    async fn recipient_state_of_lower_precedence_is_ignored() {
+        let repository = repository();
+        let uuid = Uuid::new_v4();
+        let emails = vec!["user@example.com".to_owned()];
+
+        repository
+            .save_request(
+                uuid,
+                &request(&["user@example.com"], None),
+                RequestState::Sending,
+            )
+            .await
+            .unwrap();
+
+        let state = |repository: SqliteRepository| async move {
+            let status = repository.get_request_status(uuid).await.unwrap().unwrap();
+            status.recipients[0].state
+        };
+
+        for (new_state, expected) in [
+            (RecipientState::Delivered, RecipientState::Delivered),
+            // a late Send event must not overwrite the delivery
+            (RecipientState::Sent, RecipientState::Delivered),
+            (RecipientState::Bounced, RecipientState::Bounced),
+            (RecipientState::Opened, RecipientState::Bounced),
+        ] {
+            repository
+                .set_recipients_state(uuid, &emails, new_state, None, None)
+                .await
+                .unwrap();
+
+            assert_eq!(state(repository.clone()).await, expected);
+        }
+
+        repository
+            .reset_recipient_state(uuid, &emails[0])
+            .await
+            .unwrap();
+        assert_eq!(state(repository.clone()).await, RecipientState::Received);
+    }
+
+    #[tokio::test]
+    async fn due_scheduled_requests_are_returned_until_enqueued() {
+        let repository = repository();
+        let now = Utc::now();
+        let (due, later) = (Uuid::new_v4(), Uuid::new_v4());
+
+        for (uuid, send_at) in [
+            (due, now - Duration::minutes(1)),
+            (later, now + Duration::hours(1)),
+        ] {
+            repository
+                .save_request(
+                    uuid,
+                    &request(&["user@example.com"], Some(send_at)),
+                    RequestState::Scheduled,
+                )
+                .await
+                .unwrap();
+        }
+
+        // a request that failed to be enqueued is returned again on the next check
+        for _ in 0..2 {
+            let due_requests = repository.get_due_scheduled_requests(now).await.unwrap();
+            assert_eq!(due_requests.len(), 1);
+            assert_eq!(due_requests[0].0, due);
+        }
+
+        repository
+            .mark_scheduled_request_enqueued(due, now)
+            .await
+            .unwrap();
+
+        assert!(repository
+            .get_due_scheduled_requests(now)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let status = repository.get_request_status(due).await.unwrap().unwrap();
+        assert_eq!(status.state, RequestState::Received);
+    }
+
+    #[tokio::test]
+    async fn deferred_request_is_not_marked_as_enqueued() {
+        let repository = repository();
+        let now = Utc::now();
+        let uuid = Uuid::new_v4();
+
+        // the request was processed and deferred to the next day before it was marked as enqueued
+        repository
+            .save_request(
+                uuid,
+                &request(&["user@example.com"], Some(now + Duration::days(1))),
+                RequestState::Scheduled,
+            )
+            .await
+            .unwrap();
+
+        repository
+            .mark_scheduled_request_enqueued(uuid, now)
+            .await
+            .unwrap();
+
+        let status = repository.get_request_status(uuid).await.unwrap().unwrap();
+        assert_eq!(status.state, RequestState::Scheduled);
+    }
+
+    #[tokio::test]
+    async fn due_resends_are_taken_once() {
+        let repository = repository();
+        let now = Utc::now();
+        let uuid = Uuid::new_v4();
+
+        repository
+            .save_request(
+                uuid,
+                &request(&["due@example.com", "later@example.com"], None),
+                RequestState::Finished,
+            )
+            .await
+            .unwrap();
+
+        for (email, due_at) in [
+            ("due@example.com", now - Duration::minutes(1)),
+            ("later@example.com", now + Duration::hours(1)),
+        ] {
+            repository
+                .schedule_resend(uuid, email, 1, due_at, Some("mailbox full".to_owned()))
+                .await
+                .unwrap();
+        }
+
+        let taken = repository.take_due_resends(now).await.unwrap();
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].email, "due@example.com");
+        assert_eq!(taken[0].attempt, 1);
+
+        assert!(repository.take_due_resends(now).await.unwrap().is_empty());
+        assert_eq!(
+            repository
+                .get_resend_attempts(uuid, "due@example.com")
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn retention_deletes_everything_but_scheduled_requests_and_suppressions() {
+        let repository = repository();
+        let now = Utc::now();
+        let (sent, scheduled) = (Uuid::new_v4(), Uuid::new_v4());
+
+        repository
+            .save_request(
+                sent,
+                &request(&["user@example.com"], None),
+                RequestState::Finished,
+            )
+            .await
+            .unwrap();
+        repository
+            .save_request(
+                scheduled,
+                &request(&["user@example.com"], Some(now + Duration::days(7))),
+                RequestState::Scheduled,
+            )
+            .await
+            .unwrap();
+        repository
+            .schedule_resend(sent, "user@example.com", 1, now, None)
+            .await
+            .unwrap();
+        repository
+            .pool
+            .get()
+            .unwrap()
+            .execute(
+                "INSERT INTO events (request_uuid, event_type, ses_message_id, payload, received_at)
+                VALUES (?1, 'Delivery', 'ses-id', '{}', ?2)",
+                params![sent.to_string(), now],
+            )
+            .unwrap();
+        repository
+            .register_sns_message("sns-id", now)
+            .await
+            .unwrap();
+        repository
+            .reserve_tenant_quota("acme", sent, 1, 10)
+            .await
+            .unwrap();
+        repository
+            .reserve_warmup_emails("example.com", sent, 1, now.date_naive(), 10)
+            .await
+            .unwrap();
+        repository
+            .add_suppression(&SuppressedAddress {
+                email: "bounced@example.com".to_owned(),
+                reason: SuppressionReason::Manual,
+                detail: None,
+                created_at: now,
+            })
+            .await
+            .unwrap();
+
+        let deleted = repository
+            .delete_older_than(now + Duration::days(1))
+            .await
+            .unwrap();
+
+        // the resend, the request, the event, the SNS message id and both reservations
+        assert_eq!(deleted, 6);
+
+        assert!(repository.get_request(sent).await.unwrap().is_none());
+        assert!(repository.get_request(scheduled).await.unwrap().is_some());
+
+        for table in [
+            "resends",
+            "events",
+            "sns_messages",
+            "tenant_quota_reservations",
+            "warmup_reservations",
+        ] {
+            assert_eq!(count(&repository, table), 0, "{}", table);
+        }
+
+        assert_eq!(count(&repository, "recipients"), 1);
+        assert_eq!(count(&repository, "suppressions"), 1);
+    }
+}